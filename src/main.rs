@@ -1,22 +1,80 @@
 use anyhow::{Context, Result};
-use clap::Parser;
-use hound;
-use log::{debug, info};
-use std::path::PathBuf;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
-
-use clive::{Cache, Clip, Config, FFmpeg, Timestamp};
+use clap::{Args, Parser, Subcommand};
+use log::{debug, info, warn};
+use std::path::{Path, PathBuf};
+use whisper_rs::{FullParams, WhisperContext, WhisperContextParameters};
+
+use clive::utils::{
+    desilence, export, markers, search, snap, timeline, DesilenceOutput, Envelope, Hit,
+    MarkerFormat, SnapMode, SupercutOrder, Task, TimelineFormat, WhisperConfig,
+};
+use clive::{Cache, Clip, Config, ExportConfig, ExportFormat, FFmpeg, Timestamp, Transcription};
+
+/// Whisper models that can be downloaded
+const MODELS: &[&str] = &[
+    "tiny",
+    "tiny.en",
+    "base",
+    "base.en",
+    "small",
+    "small.en",
+    "medium",
+    "medium.en",
+    "large",
+];
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-struct Args {
-    /// Path to input video file
-    #[arg(short, long)]
-    input: PathBuf,
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    /// Path to output directory
-    #[arg(short, long)]
-    output: Option<PathBuf>,
+    // Runs the full pipeline when no subcommand is given
+    #[command(flatten)]
+    run: RunArgs,
+
+    /// Enable verbose logging
+    #[arg(short, long, global = true)]
+    verbose: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Transcribe, find clips and cut them in one go
+    Run(RunArgs),
+    /// Transcribe the input and store the result in the cache
+    Transcribe(TranscribeArgs),
+    /// Search a cached transcription for keywords without cutting video
+    Search(SearchArgs),
+    /// Cut clips from a saved clip list
+    Clip(ClipArgs),
+    /// Write a cached transcription to the output directory
+    Export(ExportArgs),
+    /// Write a saved clip list as an edit decision list for an NLE
+    Timeline(TimelineArgs),
+    /// Write each keyword hit as a timeline marker or chapter without cutting video
+    Markers(MarkersArgs),
+    /// Cut the silences out of the input, or write an EDL of the parts to keep
+    Desilence(DesilenceArgs),
+    /// Manage downloaded Whisper models
+    Models {
+        #[command(subcommand)]
+        command: ModelsCommand,
+    },
+    /// Inspect and clean the cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+}
+
+// Arguments shared by subcommands that work on an input file
+#[derive(Args, Debug)]
+struct InputArgs {
+    /// Path to input video file
+    #[arg(short, long, required = true)]
+    input: Option<PathBuf>,
 
     /// Path to config file
     #[arg(long)]
@@ -30,24 +88,206 @@ struct Args {
     #[arg(short, long)]
     tracks: Option<Vec<u32>>,
 
-    /// Keywords to search for in the audio
-    #[arg(short, long, num_args = 1.., value_delimiter = ' ')]
+    /// Spoken language as a code like "es", or "auto" to detect it
+    #[arg(short, long)]
+    language: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct RunArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Path to output directory
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Keywords to search for in the audio, quote phrases like "game over"
+    #[arg(short, long, num_args = 1..)]
     clips: Option<Vec<String>>,
 
-    /// Don't clean up intermediate files
+    /// Keep the extracted audio and clips metadata after the run
     #[arg(long)]
     no_cleanup: bool,
 
-    /// Enable verbose logging
+    /// Ignore any cached transcription and run Whisper again
+    #[arg(long)]
+    retranscribe: bool,
+
+    /// Burn captions into the clips using the [captions] settings
+    #[arg(long)]
+    burn_captions: bool,
+
+    /// Also join all clips into one supercut video using the [output] settings
+    #[arg(long)]
+    supercut: bool,
+}
+
+#[derive(Args, Debug)]
+struct TranscribeArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Ignore any cached transcription and run Whisper again
+    #[arg(long)]
+    retranscribe: bool,
+}
+
+#[derive(Args, Debug)]
+struct SearchArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Keywords to search for in the audio, quote phrases like "game over"
+    #[arg(short, long, num_args = 1..)]
+    clips: Option<Vec<String>>,
+
+    /// Print the matched clips as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args, Debug)]
+struct ClipArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Path to output directory
     #[arg(short, long)]
-    verbose: bool,
+    output: Option<PathBuf>,
+
+    /// JSON clip list to cut from instead of the one saved by `search`
+    #[arg(long)]
+    from: Option<PathBuf>,
+
+    /// Burn captions into the clips using the [captions] settings
+    #[arg(long)]
+    burn_captions: bool,
+
+    /// Also join all clips into one supercut video using the [output] settings
+    #[arg(long)]
+    supercut: bool,
+}
+
+#[derive(Args, Debug)]
+struct ExportArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Path to output directory
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Formats to write (defaults to the [export] formats in the config, or srt)
+    #[arg(short, long, value_enum, num_args = 1..)]
+    format: Vec<ExportFormat>,
+}
+
+#[derive(Args, Debug)]
+struct TimelineArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Path to output directory
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// JSON clip list to use instead of the one saved by `search`
+    #[arg(long)]
+    from: Option<PathBuf>,
+
+    /// Formats to write (defaults to the [output] timelines in the config, or edl)
+    #[arg(short, long, value_enum, num_args = 1..)]
+    format: Vec<TimelineFormat>,
+}
+
+#[derive(Args, Debug)]
+struct MarkersArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Path to output directory
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Keywords to search for in the audio, quote phrases like "game over"
+    #[arg(short, long, num_args = 1..)]
+    clips: Option<Vec<String>>,
+
+    /// Formats to write (defaults to the [output] markers in the config, or chapters)
+    #[arg(short, long, value_enum, num_args = 1..)]
+    format: Vec<MarkerFormat>,
+
+    /// Ignore any cached transcription and run Whisper again
+    #[arg(long)]
+    retranscribe: bool,
+}
+
+#[derive(Args, Debug)]
+struct DesilenceArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Path to output directory
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// What to write (defaults to the [desilence] output in the config, or video)
+    #[arg(short, long, value_enum)]
+    format: Option<DesilenceOutput>,
+
+    /// Audio quieter than this many dBFS counts as silence
+    #[arg(long, allow_negative_numbers = true)]
+    threshold: Option<f64>,
+
+    /// Shortest silence in seconds that gets cut
+    #[arg(long)]
+    min_silence: Option<f64>,
+
+    /// Seconds of silence kept next to the speech on either side of a cut
+    #[arg(long)]
+    padding: Option<f64>,
+}
+
+#[derive(Subcommand, Debug)]
+enum ModelsCommand {
+    /// List available models and whether they have been downloaded
+    List,
+    /// Download a model into the cache
+    Download {
+        /// Model to download
+        name: String,
+    },
+    /// Remove a downloaded model from the cache
+    Remove {
+        /// Model to remove
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommand {
+    /// List cached inputs and their files
+    List,
+    /// Remove cached intermediate files
+    Clean {
+        /// Only remove files for this input
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+
+        /// Also remove downloaded models
+        #[arg(long, conflicts_with = "input")]
+        all: bool,
+    },
+    /// Print the cache directory
+    Path,
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
+    let cli = Cli::parse();
 
     // Initialize logging
-    if args.verbose {
+    if cli.verbose {
         env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug"))
             .format_timestamp(None)
             .init();
@@ -57,6 +297,34 @@ fn main() -> Result<()> {
             .init();
     }
 
+    // Initialize cache
+    let cache = Cache::default();
+    cache.init()?;
+
+    match cli.command {
+        None => run_command(cli.run, &cache),
+        Some(Command::Run(args)) => run_command(args, &cache),
+        Some(Command::Transcribe(args)) => transcribe_command(args, &cache),
+        Some(Command::Search(args)) => search_command(args, &cache),
+        Some(Command::Clip(args)) => clip_command(args, &cache),
+        Some(Command::Export(args)) => export_command(args, &cache),
+        Some(Command::Timeline(args)) => timeline_command(args, &cache),
+        Some(Command::Markers(args)) => markers_command(args, &cache),
+        Some(Command::Desilence(args)) => desilence_command(args, &cache),
+        Some(Command::Models { command }) => models_command(command, &cache),
+        Some(Command::Cache { command }) => cache_command(command, &cache),
+    }
+}
+
+/// Build the configuration from the config file and command line arguments
+fn load_config(
+    args: InputArgs,
+    output: Option<PathBuf>,
+    keywords: Option<Vec<String>>,
+) -> Result<Config> {
+    let input = args.input.context("Input file not specified")?;
+    let language = args.language;
+
     // Initialize configuration
     let mut config = if let Some(config_path) = args.config {
         Config::from_file(&config_path)?
@@ -65,18 +333,12 @@ fn main() -> Result<()> {
     };
 
     // Merge CLI arguments with config
-    if let Some(keywords) = args.clips {
-        let cli_config = Config::from_cli(
-            args.input.clone(),
-            args.output,
-            args.model,
-            args.tracks,
-            keywords,
-        );
+    if let Some(keywords) = keywords {
+        let cli_config = Config::from_cli(input, output, args.model, args.tracks, keywords);
         config.merge_cli(cli_config);
     } else {
-        config.input_file = Some(args.input.clone());
-        if let Some(output) = args.output {
+        config.input_file = Some(input);
+        if let Some(output) = output {
             config.output.directory = output;
         }
         if let Some(model) = args.model {
@@ -86,35 +348,501 @@ fn main() -> Result<()> {
             config.tracks.audio_tracks = tracks;
         }
     }
+    if let Some(language) = language {
+        config.clive.language = language;
+    }
 
-    // Validate configuration
-    config.validate()?;
+    Ok(config)
+}
 
-    // Initialize cache
-    let cache = Cache::default();
-    cache.init()?;
+fn run_command(args: RunArgs, cache: &Cache) -> Result<()> {
+    let mut config = load_config(args.input, args.output, args.clips)?;
+    config.captions.enabled |= args.burn_captions;
+    config.output.supercut |= args.supercut;
+    config.validate()?;
+    config.validate_export()?;
+    config.validate_output()?;
+    config.validate_captions()?;
 
     // Check FFmpeg availability
     FFmpeg::check_ffmpeg()?;
 
     // Process the video
-    process_video(&config, &cache)?;
+    process_video(&config, cache, args.retranscribe)?;
 
-    // Clean up if requested
+    // Clean up if requested, keeping the transcription for the next run
     if !args.no_cleanup {
-        cache.cleanup_for_input(config.input_file.as_ref().unwrap())?;
+        cache.cleanup_intermediate(config.input_file.as_ref().unwrap())?;
+    }
+
+    Ok(())
+}
+
+fn transcribe_command(args: TranscribeArgs, cache: &Cache) -> Result<()> {
+    let config = load_config(args.input, None, None)?;
+    config.validate_input()?;
+    FFmpeg::check_ffmpeg()?;
+
+    let transcription = load_or_transcribe(&config, cache, args.retranscribe)?;
+    info!(
+        "Transcription cached with {} words",
+        transcription.timestamps.len()
+    );
+
+    Ok(())
+}
+
+fn search_command(args: SearchArgs, cache: &Cache) -> Result<()> {
+    let config = load_config(args.input, None, args.clips)?;
+    config.validate()?;
+    let input_path = config.input_file.as_ref().unwrap();
+
+    let timestamps = load_cached_transcription(&config, cache)?.timestamps;
+    let hits = search::find_hits(&timestamps, &config);
+    let mut clips = search::build_clips(&hits, &config);
+    snap_clips(&mut clips, &timestamps, &config, cache)?;
+    cache.save_clips(input_path, clips.clone())?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&clips)?);
+    } else {
+        for clip in &clips {
+            let mut line = format!(
+                "{} - {}  {}",
+                export::format_timestamp(clip.start, '.'),
+                export::format_timestamp(clip.end, '.'),
+                clip.keywords().join(", ")
+            );
+            if clip.hits.len() > 1 {
+                line.push_str(&format!("  ({} hits)", clip.hits.len()));
+            }
+            // Show what was actually said when it wasn't an exact match
+            if clip.score() < 1.0 {
+                let heard: Vec<&str> = clip.hits.iter().map(|hit| hit.text.as_str()).collect();
+                line.push_str(&format!(
+                    "  (heard \"{}\", score {:.2})",
+                    heard.join("\", \""),
+                    clip.score()
+                ));
+            }
+            println!("{}", line);
+        }
+    }
+    info!("Found {} clips", clips.len());
+
+    Ok(())
+}
+
+fn clip_command(args: ClipArgs, cache: &Cache) -> Result<()> {
+    let mut config = load_config(args.input, args.output, None)?;
+    config.captions.enabled |= args.burn_captions;
+    config.output.supercut |= args.supercut;
+    config.validate_input()?;
+    config.validate_export()?;
+    config.validate_output()?;
+    config.validate_captions()?;
+    let input_path = config.input_file.as_ref().unwrap();
+    FFmpeg::check_ffmpeg()?;
+
+    let clips = load_clip_list(args.from, input_path, cache)?;
+
+    // Subtitles and captions need the transcription, which may not be cached
+    let (timestamps, original) = if config.output.subtitles.is_empty() && !config.captions.enabled {
+        (Vec::new(), Vec::new())
+    } else {
+        match load_cached_transcription(&config, cache) {
+            Ok(transcription) => (transcription.timestamps, transcription.original),
+            Err(e) => {
+                warn!("Skipping clip subtitles and captions: {:#}", e);
+                (Vec::new(), Vec::new())
+            }
+        }
+    };
+
+    if !config.output.timelines.is_empty() {
+        write_timelines(input_path, &clips, &config)?;
+    }
+    if !config.output.cut_clips {
+        return Ok(());
+    }
+
+    let clip_paths = create_output_clips(input_path, &clips, &timestamps, &original, &config)?;
+    info!("Successfully created {} clips", clips.len());
+
+    if config.output.supercut && !clips.is_empty() {
+        create_supercut(input_path, &clips, &clip_paths, &config)?;
+    }
+
+    Ok(())
+}
+
+fn export_command(args: ExportArgs, cache: &Cache) -> Result<()> {
+    let mut config = load_config(args.input, args.output, None)?;
+    config.validate_input()?;
+    config.validate_export()?;
+
+    if !args.format.is_empty() {
+        config.export.formats = args.format;
+    } else if config.export.formats.is_empty() {
+        config.export.formats = vec![ExportFormat::Srt];
+    }
+
+    let transcription = load_cached_transcription(&config, cache)?;
+    export_transcript(&transcription, &config)
+}
+
+fn timeline_command(args: TimelineArgs, cache: &Cache) -> Result<()> {
+    let mut config = load_config(args.input, args.output, None)?;
+    config.validate_input()?;
+    FFmpeg::check_ffmpeg()?;
+
+    if !args.format.is_empty() {
+        config.output.timelines = args.format;
+    } else if config.output.timelines.is_empty() {
+        config.output.timelines = vec![TimelineFormat::Edl];
+    }
+
+    let input_path = config.input_file.as_ref().unwrap();
+    let clips = load_clip_list(args.from, input_path, cache)?;
+    write_timelines(input_path, &clips, &config)
+}
+
+fn markers_command(args: MarkersArgs, cache: &Cache) -> Result<()> {
+    let mut config = load_config(args.input, args.output, args.clips)?;
+    config.validate()?;
+    FFmpeg::check_ffmpeg()?;
+
+    if !args.format.is_empty() {
+        config.output.markers = args.format;
+    } else if config.output.markers.is_empty() {
+        config.output.markers = vec![MarkerFormat::Chapters];
+    }
+
+    let timestamps = load_or_transcribe(&config, cache, args.retranscribe)?.timestamps;
+    let hits = search::find_hits(&timestamps, &config);
+    write_markers(config.input_file.as_ref().unwrap(), &hits, &config)?;
+    info!("Wrote {} markers", hits.len());
+
+    Ok(())
+}
+
+fn desilence_command(args: DesilenceArgs, cache: &Cache) -> Result<()> {
+    let mut config = load_config(args.input, args.output, None)?;
+    let desilence = &mut config.desilence;
+    desilence.output = args.format.unwrap_or(desilence.output);
+    desilence.threshold = args.threshold.unwrap_or(desilence.threshold);
+    desilence.min_silence = args.min_silence.unwrap_or(desilence.min_silence);
+    desilence.padding = args.padding.unwrap_or(desilence.padding);
+    config.validate_input()?;
+    config.validate_desilence()?;
+    config.validate_output()?;
+    FFmpeg::check_ffmpeg()?;
+
+    let input_path = config.input_file.as_ref().unwrap();
+    let desilence = &config.desilence;
+    let envelope = audio_envelope(&config, cache)?;
+    let silences = envelope.silences(desilence.threshold, desilence.min_silence);
+    let duration = FFmpeg::get_duration(input_path)?;
+    let ranges = desilence::kept_ranges(&silences, duration, desilence.padding);
+    if ranges.is_empty() {
+        anyhow::bail!("The input is silent, there is nothing to keep");
+    }
+    let kept: f64 = ranges.iter().map(|(start, end)| end - start).sum();
+    info!(
+        "Cutting {} silences, keeping {:.1}s of {:.1}s",
+        silences.len(),
+        kept,
+        duration
+    );
+
+    let stem = input_path.file_stem().unwrap().to_string_lossy();
+    let name = format!("{}_desilenced", stem);
+    std::fs::create_dir_all(&config.output.directory)?;
+    let path = match desilence.output {
+        DesilenceOutput::Video => {
+            let path = config.output.directory.join(format!("{}.mp4", name));
+            FFmpeg::keep_ranges(
+                input_path,
+                &path,
+                &ranges,
+                &config.tracks.audio_tracks,
+                &config.output,
+            )?;
+            path
+        }
+        DesilenceOutput::Edl => {
+            let info = FFmpeg::get_video_info(input_path)?;
+            timeline::write(
+                &desilence::ranges_to_clips(&ranges),
+                TimelineFormat::Edl,
+                input_path,
+                &info,
+                &config.output.directory,
+                &name,
+            )?
+        }
+    };
+    info!("Wrote {}", path.display());
+
+    Ok(())
+}
+
+/// Load clips from a JSON clip list, or the list saved by the last search
+fn load_clip_list(from: Option<PathBuf>, input_path: &Path, cache: &Cache) -> Result<Vec<Clip>> {
+    match from {
+        Some(path) => {
+            let json = std::fs::read_to_string(&path).context("Failed to read clip list")?;
+            serde_json::from_str(&json).context("Failed to parse clip list")
+        }
+        None => cache
+            .load_clips(input_path)
+            .context("No saved clips for this input, run `clive search` first"),
+    }
+}
+
+/// Write the clips in every configured timeline format
+fn write_timelines(input_path: &Path, clips: &[Clip], config: &Config) -> Result<()> {
+    let stem = input_path.file_stem().unwrap().to_string_lossy();
+    let info = FFmpeg::get_video_info(input_path)?;
+    std::fs::create_dir_all(&config.output.directory)?;
+
+    for &format in &config.output.timelines {
+        let path = timeline::write(
+            clips,
+            format,
+            input_path,
+            &info,
+            &config.output.directory,
+            &stem,
+        )?;
+        info!("Wrote {}", path.display());
     }
 
     Ok(())
 }
 
-fn process_video(config: &Config, cache: &Cache) -> Result<()> {
+/// Write the keyword hits in every configured marker format
+fn write_markers(input_path: &Path, hits: &[Hit], config: &Config) -> Result<()> {
+    let name = format!(
+        "{}_markers",
+        input_path.file_stem().unwrap().to_string_lossy()
+    );
+    let info = FFmpeg::get_video_info(input_path)?;
+    std::fs::create_dir_all(&config.output.directory)?;
+
+    for &format in &config.output.markers {
+        let path = markers::write(hits, format, &info, &config.output.directory, &name)?;
+        info!("Wrote {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Write the transcript in every configured export format
+fn export_transcript(transcription: &Transcription, config: &Config) -> Result<()> {
+    let input_path = config.input_file.as_ref().unwrap();
+    let stem = input_path.file_stem().unwrap().to_string_lossy();
+    std::fs::create_dir_all(&config.output.directory)?;
+
+    for &format in &config.export.formats {
+        let path = export::write(
+            &transcription.timestamps,
+            &transcription.original,
+            format,
+            &config.export,
+            &config.output.directory,
+            &stem,
+        )?;
+        info!("Wrote {}", path.display());
+    }
+
+    Ok(())
+}
+
+fn models_command(command: ModelsCommand, cache: &Cache) -> Result<()> {
+    match command {
+        ModelsCommand::List => {
+            for model in MODELS {
+                let path = cache.model_path(model);
+                match std::fs::metadata(&path) {
+                    Ok(metadata) => println!(
+                        "{:<10} downloaded ({} MB)",
+                        model,
+                        metadata.len() / 1_000_000
+                    ),
+                    Err(_) => println!("{:<10} not downloaded", model),
+                }
+            }
+        }
+        ModelsCommand::Download { name } => {
+            get_model_url(&name)?;
+            download_model_if_needed(&name, cache)?;
+        }
+        ModelsCommand::Remove { name } => {
+            let path = cache.model_path(&name);
+            if !path.exists() {
+                anyhow::bail!("Model {} is not downloaded", name);
+            }
+            std::fs::remove_file(&path).context("Failed to remove model")?;
+            info!("Removed {} model", name);
+        }
+    }
+
+    Ok(())
+}
+
+fn cache_command(command: CacheCommand, cache: &Cache) -> Result<()> {
+    match command {
+        CacheCommand::List => {
+            for entry in cache.entries()? {
+                let files = cache.entry_files(&entry.fingerprint)?;
+                println!(
+                    "{}  {}  ({} files)",
+                    entry.fingerprint,
+                    entry.path.display(),
+                    files.len()
+                );
+            }
+        }
+        CacheCommand::Clean { input, all } => {
+            if let Some(input) = input {
+                cache.cleanup_for_input(&input)?;
+            } else if all {
+                cache.cleanup()?;
+            } else {
+                cache.clear()?;
+            }
+            info!("Cache cleaned");
+        }
+        CacheCommand::Path => println!("{}", cache.dir().display()),
+    }
+
+    Ok(())
+}
+
+/// Load the cached transcription for the configured input, model, language, task and tracks
+///
+/// Falls back to a transcription made with other `[whisper]` settings rather than failing.
+fn load_cached_transcription(config: &Config, cache: &Cache) -> Result<Transcription> {
+    let input_path = config.input_file.as_ref().unwrap();
+    let (model, language, task, tracks) = (
+        &config.clive.model,
+        &config.clive.language,
+        config.clive.task,
+        &config.tracks.audio_tracks,
+    );
+
+    if let Some(transcription) = cache.find_transcription(
+        input_path,
+        model,
+        language,
+        task,
+        tracks,
+        &config.decoding_key(),
+    ) {
+        return Ok(transcription);
+    }
+    let transcription = cache
+        .find_transcription_any_decoding(input_path, model, language, task, tracks)
+        .context("No cached transcription for this input, run `clive transcribe` first")?;
+    warn!(
+        "Cached transcription was made with other [whisper] settings or prompt keywords, \
+         run `clive transcribe --retranscribe` to apply them"
+    );
+    Ok(transcription)
+}
+
+fn process_video(config: &Config, cache: &Cache, retranscribe: bool) -> Result<()> {
     let input_path = config.input_file.as_ref().unwrap();
     info!("Processing video: {}", input_path.display());
 
+    let transcription = load_or_transcribe(config, cache, retranscribe)?;
+    let timestamps = &transcription.timestamps;
+
+    // Step 3.75: Export the transcript in any configured formats
+    if !config.export.formats.is_empty() {
+        debug!("Step 3.75: Exporting transcript");
+        export_transcript(&transcription, config)?;
+    }
+
+    // Step 4: Find clips based on keywords
+    debug!("Step 4: Finding clips based on keywords");
+    let hits = search::find_hits(timestamps, config);
+    let mut clips = search::build_clips(&hits, config);
+    snap_clips(&mut clips, timestamps, config, cache)?;
+    debug!("Found {} keyword hits in {} clips", hits.len(), clips.len());
+
+    // Step 4.25: Write markers for the raw keyword hits
+    if !config.output.markers.is_empty() {
+        debug!("Step 4.25: Writing markers");
+        write_markers(input_path, &hits, config)?;
+    }
+
+    // Step 4.5: Save clips to cache
+    debug!("Step 4.5: Saving clips to cache");
+    cache.save_clips(input_path, clips.clone())?;
+
+    // Step 4.75: Write edit decision lists
+    if !config.output.timelines.is_empty() {
+        debug!("Step 4.75: Writing timelines");
+        write_timelines(input_path, &clips, config)?;
+    }
+    if !config.output.cut_clips {
+        return Ok(());
+    }
+
+    // Step 5: Create output clips
+    debug!("Step 5: Creating output clips");
+    let clip_paths = create_output_clips(
+        input_path,
+        &clips,
+        timestamps,
+        &transcription.original,
+        config,
+    )?;
+    info!("Successfully created {} clips", clips.len());
+
+    // Step 6: Join the clips into a supercut
+    if config.output.supercut && !clips.is_empty() {
+        debug!("Step 6: Creating supercut");
+        create_supercut(input_path, &clips, &clip_paths, config)?;
+    }
+
+    Ok(())
+}
+
+/// Reuse a valid cached transcription, or run steps 1-3 to create one
+fn load_or_transcribe(config: &Config, cache: &Cache, retranscribe: bool) -> Result<Transcription> {
+    let input_path = config.input_file.as_ref().unwrap();
+
+    let cached = if retranscribe {
+        None
+    } else {
+        cache.find_transcription(
+            input_path,
+            &config.clive.model,
+            &config.clive.language,
+            config.clive.task,
+            &config.tracks.audio_tracks,
+            &config.decoding_key(),
+        )
+    };
+
+    match cached {
+        Some(transcription) => {
+            info!("Using cached transcription, skipping Whisper");
+            Ok(transcription)
+        }
+        None => transcribe_video(config, cache),
+    }
+}
+
+fn transcribe_video(config: &Config, cache: &Cache) -> Result<Transcription> {
+    let input_path = config.input_file.as_ref().unwrap();
+
     // Step 1: Check/Download model
     debug!("Step 1: Checking/Downloading model");
-    download_model_if_needed(config, cache)?;
+    download_model_if_needed(&config.clive.model, cache)?;
 
     // Step 2: Extract audio tracks
     debug!("Step 2: Extracting audio tracks");
@@ -123,47 +851,55 @@ fn process_video(config: &Config, cache: &Cache) -> Result<()> {
 
     // Step 3: Transcribe audio and combine results
     debug!("Step 3: Transcribing audio");
-    let timestamps = transcribe_audio_tracks(&config.clive.model, &audio_paths, cache)?;
-    debug!("Found {} timestamp segments", timestamps.len());
+    let transcripts = transcribe_audio_tracks(
+        &config.clive.model,
+        &config.clive.language,
+        config.clive.task,
+        &config.whisper,
+        config.whisper_prompt().as_deref(),
+        &audio_paths,
+        cache,
+    )?;
+    debug!("Found {} timestamp segments", transcripts.timestamps.len());
 
     // Step 3.5: Save timestamps to cache
     debug!("Step 3.5: Saving timestamps to cache");
-    cache.save_transcription(input_path, timestamps.clone())?;
+    let transcription = Transcription {
+        model: config.clive.model.clone(),
+        language: config.clive.language.clone(),
+        task: config.clive.task,
+        decoding: config.decoding_key(),
+        tracks: config.tracks.audio_tracks.clone(),
+        detected_languages: transcripts.languages,
+        timestamps: transcripts.timestamps,
+        original: transcripts.original,
+    };
+    cache.save_transcription(input_path, &transcription)?;
     debug!("Successfully saved timestamps to cache");
 
-    // Step 4: Find clips based on keywords
-    debug!("Step 4: Finding clips based on keywords");
-    let clips = find_clips(&timestamps, config)?;
-    debug!("Found {} clips matching keywords", clips.len());
-
-    // Step 5: Create output clips
-    debug!("Step 5: Creating output clips");
-    create_output_clips(input_path, &clips, &config.output.directory)?;
-    info!("Successfully created {} clips", clips.len());
-
-    Ok(())
+    Ok(transcription)
 }
 
-fn download_model_if_needed(config: &Config, cache: &Cache) -> Result<()> {
-    if !cache.model_exists(&config.clive.model) {
-        info!("Downloading {} model...", config.clive.model);
-        let url = get_model_url(&config.clive.model)?;
+fn download_model_if_needed(model: &str, cache: &Cache) -> Result<()> {
+    if !cache.model_exists(model) {
+        info!("Downloading {} model...", model);
+        let url = get_model_url(model)?;
         debug!("Model URL: {}", url);
 
         let mut response = ureq::get(&url).call().context("Failed to download model")?;
         debug!("Got response from server");
 
-        let mut file = std::fs::File::create(cache.model_path(&config.clive.model))?;
+        let mut file = std::fs::File::create(cache.model_path(model))?;
         debug!(
             "Created model file at {}",
-            cache.model_path(&config.clive.model).display()
+            cache.model_path(model).display()
         );
         std::io::copy(&mut response.body_mut().as_reader(), &mut file)?;
         info!("Successfully downloaded model");
     } else {
         debug!(
             "Model already exists at {}",
-            cache.model_path(&config.clive.model).display()
+            cache.model_path(model).display()
         );
     }
     Ok(())
@@ -193,7 +929,7 @@ fn extract_audio_tracks(config: &Config, cache: &Cache) -> Result<Vec<PathBuf>>
 
     for &track in &config.tracks.audio_tracks {
         debug!("Processing audio track {}", track);
-        let output_path = cache.audio_path(input_path, track);
+        let output_path = cache.audio_path(input_path, track)?;
         debug!("Extracting to {}", output_path.display());
         FFmpeg::extract_audio_tracks(input_path, &output_path, &[track])?;
         audio_paths.push(output_path);
@@ -203,7 +939,48 @@ fn extract_audio_tracks(config: &Config, cache: &Cache) -> Result<Vec<PathBuf>>
     Ok(audio_paths)
 }
 
-fn load_audio(path: &PathBuf) -> Result<Vec<f32>> {
+/// Snap clip boundaries as set in `[snap]`, reading the audio tracks for silence
+fn snap_clips(
+    clips: &mut [Clip],
+    timestamps: &[Timestamp],
+    config: &Config,
+    cache: &Cache,
+) -> Result<()> {
+    if config.snap.mode != SnapMode::Silence {
+        snap::snap_clips(clips, timestamps, &config.snap);
+        return Ok(());
+    }
+
+    let envelope = audio_envelope(config, cache)?;
+    let silences = envelope.silences(config.snap.silence_threshold, config.snap.min_silence);
+    debug!("Found {} pauses to snap clips to", silences.len());
+    let edges = snap::silence_edges(&silences);
+    snap::snap_to_edges(clips, &edges, config.snap.tolerance);
+    Ok(())
+}
+
+/// Energy envelope of the configured audio tracks, reusing extracted audio from the cache
+///
+/// A window only counts as quiet when every track is quiet.
+fn audio_envelope(config: &Config, cache: &Cache) -> Result<Envelope> {
+    let input_path = config.input_file.as_ref().unwrap();
+    let mut envelope: Option<Envelope> = None;
+    for &track in &config.tracks.audio_tracks {
+        let audio_path = cache.audio_path(input_path, track)?;
+        if !audio_path.exists() {
+            debug!("Extracting audio track {} for silence detection", track);
+            FFmpeg::extract_audio_tracks(input_path, &audio_path, &[track])?;
+        }
+        let track_envelope = Envelope::from_wav(&audio_path)?;
+        envelope = Some(match envelope {
+            Some(envelope) => envelope.loudest(&track_envelope),
+            None => track_envelope,
+        });
+    }
+    envelope.context("No audio tracks specified")
+}
+
+fn load_audio(path: &Path) -> Result<Vec<f32>> {
     debug!("Loading WAV file: {}", path.display());
     let reader = hound::WavReader::open(path).context("Failed to open WAV file")?;
 
@@ -217,11 +994,29 @@ fn load_audio(path: &PathBuf) -> Result<Vec<f32>> {
     Ok(float_samples)
 }
 
+/// Words of every audio track, as returned by `transcribe_audio_tracks`
+struct TrackTranscripts {
+    /// Words used for keyword matching, in English for the translate task
+    timestamps: Vec<Timestamp>,
+    /// Words in the spoken language, only kept for the translate task
+    original: Vec<Timestamp>,
+    /// Spoken language of each track
+    languages: Vec<String>,
+}
+
+/// Transcribe each audio track, also translating it to English for the translate task
 fn transcribe_audio_tracks(
     model_name: &str,
+    language: &str,
+    task: Task,
+    whisper: &WhisperConfig,
+    prompt: Option<&str>,
     audio_paths: &[PathBuf],
     cache: &Cache,
-) -> Result<Vec<Timestamp>> {
+) -> Result<TrackTranscripts> {
+    if let Some(prompt) = prompt {
+        debug!("Prompting Whisper with: {}", prompt);
+    }
     debug!("Loading Whisper model: {}", model_name);
     let ctx = WhisperContext::new_with_params(
         &cache.model_path(model_name).to_string_lossy(),
@@ -230,7 +1025,14 @@ fn transcribe_audio_tracks(
     .context("Failed to load Whisper model")?;
     debug!("Successfully loaded Whisper model");
 
-    let mut all_timestamps: Vec<Timestamp> = Vec::new();
+    let mut transcripts = TrackTranscripts {
+        timestamps: Vec::new(),
+        original: Vec::new(),
+        languages: Vec::new(),
+    };
+    // Segment indices keep counting across tracks so they stay unique
+    let mut segment_offset = 0;
+    let mut original_offset = 0;
 
     for (i, audio_path) in audio_paths.iter().enumerate() {
         debug!("Processing audio file {} of {}", i + 1, audio_paths.len());
@@ -246,187 +1048,373 @@ fn transcribe_audio_tracks(
         whisper_rs::convert_integer_to_float_audio(&i16_samples, &mut inter_samples)
             .context("Failed to convert audio to float")?;
 
-        // Create parameters for transcription
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-        params.set_language(Some("en"));
-        params.set_print_special(false);
-        params.set_print_progress(false);
-        params.set_print_realtime(false);
-
-        // Create state and run transcription
-        let mut state = ctx.create_state().context("Failed to create state")?;
-        debug!("Running transcription on entire audio file");
-        state
-            .full(params, &inter_samples)
-            .context("Failed to process audio")?;
-
-        let num_segments = state
-            .full_n_segments()
-            .context("Failed to get number of segments")?;
-        debug!("Found {} segments", num_segments);
-
-        for i in 0..num_segments {
-            let text = state
-                .full_get_segment_text(i)
-                .context("Failed to get segment text")?;
-            let start = state
-                .full_get_segment_t0(i)
-                .context("Failed to get segment start")? as f64
-                * 0.01;
-            let end = state
-                .full_get_segment_t1(i)
-                .context("Failed to get segment end")? as f64
-                * 0.01;
-
-            // Get token-level timestamps for this segment
-            let num_tokens = state
-                .full_n_tokens(i)
-                .context("Failed to get number of tokens")?;
-
-            // If there are no tokens, just add the segment
-            if num_tokens == 0 {
-                debug!("Segment {}: {}s -> {}s: {}", i, start, end, text);
-                // check if the last text and the new text are the same
-                // if they are the same, don't add the segment
-                // if they are different, add the segment
-                if all_timestamps.last().is_some() {
-                    if all_timestamps.last().unwrap().text == text {
-                        continue;
-                    }
-                }
-                all_timestamps.push(Timestamp { start, end, text });
-                continue;
-            }
+        let (timestamps, segments, detected) = run_whisper(
+            &ctx,
+            &inter_samples,
+            language,
+            false,
+            whisper,
+            prompt,
+            original_offset,
+        )?;
+        if language == "auto" {
+            info!("Detected language {} in {}", detected, audio_path.display());
+        }
 
-            // Process each token in the segment
-            let mut token_start = None;
-            let mut current_text = String::new();
-
-            for t in 0..num_tokens {
-                let token = state
-                    .full_get_token_text(i, t)
-                    .context("Failed to get token text")?;
-                let token_data = state
-                    .full_get_token_data(i, t)
-                    .context("Failed to get token data")?;
-
-                // Skip special tokens and empty tokens
-                if token_data.id >= 50258 || token.trim().is_empty() {
-                    continue;
-                }
+        if task == Task::Translate {
+            // Translate from the detected language rather than detecting it again
+            debug!("Translating {} to English", detected);
+            let (translated, translated_segments, _) = run_whisper(
+                &ctx,
+                &inter_samples,
+                &detected,
+                true,
+                whisper,
+                prompt,
+                segment_offset,
+            )?;
+            transcripts.timestamps.extend(translated);
+            transcripts.original.extend(timestamps);
+            segment_offset += translated_segments;
+            original_offset += segments;
+        } else {
+            transcripts.timestamps.extend(timestamps);
+            segment_offset += segments;
+            original_offset += segments;
+        }
+        transcripts.languages.push(detected);
+    }
 
-                // Get token time from whisper
-                let token_time = state
-                    .full_get_segment_t0(i)
-                    .context("Failed to get segment start")?
-                    as f64
-                    * 0.01;
+    debug!("Total timestamps found: {}", transcripts.timestamps.len());
+    Ok(transcripts)
+}
 
-                if token_start.is_none() {
-                    token_start = Some(token_time);
-                }
+/// Run Whisper over the samples of one track
+///
+/// Returns the words, the number of segments and the language of the audio.
+/// Segment indices of the words start at `segment_offset`, decoding follows `[whisper]`
+/// and `prompt` biases Whisper towards the names and keywords in it.
+fn run_whisper(
+    ctx: &WhisperContext,
+    samples: &[f32],
+    language: &str,
+    translate: bool,
+    whisper: &WhisperConfig,
+    prompt: Option<&str>,
+    segment_offset: usize,
+) -> Result<(Vec<Timestamp>, usize, String)> {
+    // Create parameters for transcription
+    let mut params = FullParams::new(whisper.sampling_strategy());
+    // Whisper detects the language from the first 30 seconds for "auto"
+    params.set_language(Some(language));
+    params.set_translate(translate);
+    if let Some(threads) = whisper.threads {
+        params.set_n_threads(threads as i32);
+    }
+    params.set_temperature(whisper.temperature);
+    params.set_temperature_inc(whisper.temperature_inc);
+    params.set_no_speech_thold(whisper.no_speech_threshold);
+    params.set_logprob_thold(whisper.logprob_threshold);
+    params.set_suppress_blank(whisper.suppress_blank);
+    if whisper.max_len > 0 {
+        params.set_max_len(whisper.max_len as i32);
+        // Otherwise long segments are split mid-word, breaking up the words we search
+        params.set_split_on_word(true);
+    }
+    if let Some(prompt) = prompt {
+        params.set_initial_prompt(prompt);
+    }
+    params.set_token_timestamps(true);
+    params.set_print_special(false);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+
+    // Create state and run transcription
+    let mut state = ctx.create_state().context("Failed to create state")?;
+    debug!("Running transcription on entire audio file");
+    state
+        .full(params, samples)
+        .context("Failed to process audio")?;
+
+    let lang_id = state
+        .full_lang_id_from_state()
+        .context("Failed to get transcription language")?;
+    let detected = whisper_rs::get_lang_str(lang_id).unwrap_or(language);
+
+    let num_segments = state
+        .full_n_segments()
+        .context("Failed to get number of segments")?;
+    debug!("Found {} segments", num_segments);
+
+    let mut timestamps: Vec<Timestamp> = Vec::new();
+    for i in 0..num_segments {
+        let segment = segment_offset + i as usize;
+        let text = state
+            .full_get_segment_text(i)
+            .context("Failed to get segment text")?;
+        let start = state
+            .full_get_segment_t0(i)
+            .context("Failed to get segment start")? as f64
+            * 0.01;
+        let end = state
+            .full_get_segment_t1(i)
+            .context("Failed to get segment end")? as f64
+            * 0.01;
+
+        // Get token-level timestamps for this segment
+        let num_tokens = state
+            .full_n_tokens(i)
+            .context("Failed to get number of tokens")?;
+
+        // If there are no tokens, just add the segment
+        if num_tokens == 0 {
+            debug!("Segment {}: {}s -> {}s: {}", i, start, end, text);
+            // skip the segment if it repeats the previous text
+            if timestamps.last().is_some_and(|last| last.text == text) {
+                continue;
+            }
+            timestamps.push(Timestamp {
+                start,
+                end,
+                text,
+                segment,
+            });
+            continue;
+        }
 
-                // Add the token text
-                current_text.push_str(&token);
-
-                // If this is the last token or the next token is a new word/sentence
-                let is_last_token = t == num_tokens - 1;
-                let is_word_end = token.ends_with(' ') || token.ends_with('\n');
-
-                if is_last_token || is_word_end {
-                    let trimmed_text = current_text.trim();
-                    if !trimmed_text.is_empty() {
-                        debug!(
-                            "Adding word: '{}' ({} -> {})",
-                            trimmed_text,
-                            token_start.unwrap(),
-                            token_time
-                        );
-                        all_timestamps.push(Timestamp {
-                            start: token_start.unwrap(),
-                            end: token_time,
-                            text: trimmed_text.to_string(),
-                        });
-                    }
-                    token_start = None;
-                    current_text.clear();
-                }
+        // Group tokens into words, each word spanning from the start of
+        // its first token to the end of its last token
+        let mut word_start = None;
+        let mut word_end = start;
+        let mut current_text = String::new();
+
+        for t in 0..num_tokens {
+            let token = state
+                .full_get_token_text(i, t)
+                .context("Failed to get token text")?;
+            let token_data = state
+                .full_get_token_data(i, t)
+                .context("Failed to get token data")?;
+
+            // Skip special tokens (end of text, timestamps, language, etc.)
+            if token_data.id >= ctx.token_eot() {
+                continue;
             }
 
-            // Add any remaining text as a segment
-            if !current_text.trim().is_empty() {
-                let trimmed_text = current_text.trim();
-                debug!(
-                    "Adding remaining word: '{}' ({} -> {})",
-                    trimmed_text,
-                    token_start.unwrap_or(start),
-                    end
+            // Whisper marks the beginning of a new word with a leading space
+            if token.starts_with(char::is_whitespace) && !current_text.trim().is_empty() {
+                push_word(
+                    &mut timestamps,
+                    &current_text,
+                    word_start.unwrap_or(start),
+                    word_end,
+                    segment,
                 );
-                all_timestamps.push(Timestamp {
-                    start: token_start.unwrap_or(start),
-                    end,
-                    text: trimmed_text.to_string(),
-                });
+                word_start = None;
+                current_text.clear();
             }
-        }
-    }
 
-    debug!("Total timestamps found: {}", all_timestamps.len());
-    Ok(all_timestamps)
-}
+            if token.trim().is_empty() {
+                continue;
+            }
 
-fn find_clips(timestamps: &[Timestamp], config: &Config) -> Result<Vec<Clip>> {
-    let mut clips: Vec<Clip> = Vec::new();
+            // Token times are in centiseconds, clamped to the segment bounds
+            let token_start = (token_data.t0 as f64 * 0.01).clamp(start, end);
+            let token_end = (token_data.t1 as f64 * 0.01).clamp(token_start, end);
 
-    for (keyword, clip_config) in &config.clips {
-        for timestamp in timestamps {
-            if timestamp
-                .text
-                .to_lowercase()
-                .split_whitespace()
-                .map(|word| word.trim_matches(|c: char| c.is_ascii_punctuation()))
-                .any(|word| word == keyword.to_lowercase())
-            {
-                clips.push(Clip {
-                    start: (timestamp.start - clip_config.start_time as f64).max(0.0),
-                    end: timestamp.end + clip_config.end_time as f64,
-                    keyword: keyword.clone(),
-                });
+            if word_start.is_none() {
+                word_start = Some(token_start);
             }
+            word_end = token_end;
+            current_text.push_str(&token);
         }
-    }
-
-    // Merge overlapping clips
-    clips.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap());
-    let mut merged_clips: Vec<Clip> = Vec::new();
 
-    for clip in clips {
-        if let Some(last) = merged_clips.last_mut() {
-            if clip.start <= last.end {
-                last.end = last.end.max(clip.end);
-                last.keyword = format!("{}, {}", last.keyword, clip.keyword);
-                continue;
-            }
+        // Add the last word of the segment
+        if !current_text.trim().is_empty() {
+            push_word(
+                &mut timestamps,
+                &current_text,
+                word_start.unwrap_or(start),
+                word_end,
+                segment,
+            );
         }
-        merged_clips.push(clip);
     }
 
-    Ok(merged_clips)
+    Ok((timestamps, num_segments as usize, detected.to_string()))
 }
 
-fn create_output_clips(input_path: &PathBuf, clips: &[Clip], output_dir: &PathBuf) -> Result<()> {
+fn push_word(timestamps: &mut Vec<Timestamp>, text: &str, start: f64, end: f64, segment: usize) {
+    let text = text.trim();
+    debug!("Adding word: '{}' ({} -> {})", text, start, end);
+    timestamps.push(Timestamp {
+        start,
+        end,
+        text: text.to_string(),
+        segment,
+    });
+}
+
+fn create_output_clips(
+    input_path: &Path,
+    clips: &[Clip],
+    timestamps: &[Timestamp],
+    original: &[Timestamp],
+    config: &Config,
+) -> Result<Vec<PathBuf>> {
+    let output_dir = &config.output.directory;
     std::fs::create_dir_all(output_dir)?;
+    let mut clip_paths = Vec::with_capacity(clips.len());
+
+    let burn_captions = config.captions.enabled && !timestamps.is_empty();
+    let resolution = if burn_captions {
+        FFmpeg::get_resolution(input_path)?
+    } else {
+        (0, 0)
+    };
 
     for (i, clip) in clips.iter().enumerate() {
-        let output_path = output_dir.join(format!(
-            "clip_{}_{}_{}.mp4",
+        let name = format!(
+            "clip_{}_{}_{}",
             i + 1,
-            clip.keyword.replace([' ', ','], "_"),
+            export::sanitize_file_name(&clip.keyword),
             input_path.file_stem().unwrap().to_string_lossy()
-        ));
+        );
+        let output_path = output_dir.join(format!("{}.mp4", name));
+        let clip_timestamps = export::clip_timestamps(timestamps, clip.start, clip.end);
+
+        if burn_captions {
+            let captions = render_captions(&clip_timestamps, config, resolution);
+            let subtitles = tempfile::Builder::new().suffix(".ass").tempfile()?;
+            std::fs::write(subtitles.path(), captions)?;
+            FFmpeg::create_captioned_clip(
+                input_path,
+                &output_path,
+                clip.start,
+                clip.end,
+                subtitles.path(),
+                &config.output,
+            )
+            .with_context(|| format!("Failed to create clip {}", output_path.display()))?;
+        } else {
+            FFmpeg::create_clip(
+                input_path,
+                &output_path,
+                clip.start,
+                clip.end,
+                &config.output,
+            )
+            .with_context(|| format!("Failed to create clip {}", output_path.display()))?;
+        }
+
+        // Write subtitle sidecars with timings relative to the clip start
+        if !config.output.subtitles.is_empty() && !timestamps.is_empty() {
+            let clip_original = export::clip_timestamps(original, clip.start, clip.end);
+            for &format in &config.output.subtitles {
+                export::write(
+                    &clip_timestamps,
+                    &clip_original,
+                    format,
+                    &config.export,
+                    output_dir,
+                    &name,
+                )?;
+            }
+        }
 
-        FFmpeg::create_clip(input_path, &output_path, clip.start, clip.end)?;
+        clip_paths.push(output_path);
     }
 
-    Ok(())
+    Ok(clip_paths)
+}
+
+/// Join the created clips into a single video, with optional title cards
+fn create_supercut(
+    input_path: &Path,
+    clips: &[Clip],
+    clip_paths: &[PathBuf],
+    config: &Config,
+) -> Result<PathBuf> {
+    let options = &config.output;
+
+    let mut order: Vec<usize> = (0..clips.len()).collect();
+    order.sort_by(|&a, &b| clips[a].start.total_cmp(&clips[b].start));
+    if options.supercut_order == SupercutOrder::Keyword {
+        // Groups are ordered by their first appearance; the sort is stable
+        let mut keywords: Vec<&str> = Vec::new();
+        for &i in &order {
+            if !keywords.contains(&clips[i].keyword.as_str()) {
+                keywords.push(&clips[i].keyword);
+            }
+        }
+        order.sort_by_key(|&i| keywords.iter().position(|k| *k == clips[i].keyword));
+    }
+
+    // Title cards and crossfades need every part re-encoded to a common format
+    let reencode = options.title_cards || options.crossfade > 0.0;
+    let frame_rate = if reencode {
+        FFmpeg::get_frame_rate(input_path)?
+    } else {
+        String::new()
+    };
+    let resolution = if options.title_cards {
+        FFmpeg::get_resolution(input_path)?
+    } else {
+        (0, 0)
+    };
+
+    let cards_dir = tempfile::tempdir()?;
+    let mut parts: Vec<PathBuf> = Vec::new();
+    let mut previous_keyword = None;
+    for &i in &order {
+        let clip = &clips[i];
+        let new_group = options.supercut_order == SupercutOrder::Timeline
+            || previous_keyword != Some(&clip.keyword);
+        if options.title_cards && new_group {
+            let card_path = cards_dir.path().join(format!("card_{}.mp4", parts.len()));
+            FFmpeg::create_title_card(
+                &clip.keyword,
+                &card_path,
+                resolution,
+                &frame_rate,
+                &config.captions,
+                options,
+            )?;
+            parts.push(card_path);
+        }
+        parts.push(clip_paths[i].clone());
+        previous_keyword = Some(&clip.keyword);
+    }
+
+    let output_path = options.directory.join(format!(
+        "supercut_{}.mp4",
+        input_path.file_stem().unwrap().to_string_lossy()
+    ));
+    let parts: Vec<&Path> = parts.iter().map(PathBuf::as_path).collect();
+    if reencode {
+        FFmpeg::join_clips(&parts, &output_path, &frame_rate, options)
+    } else {
+        // Clips cut from the same input can be joined without re-encoding
+        FFmpeg::combine_clips(&parts, &output_path)
+    }
+    .with_context(|| format!("Failed to create supercut {}", output_path.display()))?;
+
+    info!("Created supercut {}", output_path.display());
+    Ok(output_path)
+}
+
+/// Render burned-in captions for a clip as an ASS script
+fn render_captions(
+    clip_timestamps: &[Timestamp],
+    config: &Config,
+    resolution: (u32, u32),
+) -> String {
+    let cue_limits = ExportConfig {
+        max_line_length: config.captions.max_line_length,
+        max_lines: config.captions.max_lines,
+        max_cue_duration: config.export.max_cue_duration,
+        ..Default::default()
+    };
+    let cues = export::build_cues(clip_timestamps, &cue_limits);
+    export::to_ass(&cues, &config.captions, resolution.0, resolution.1)
 }
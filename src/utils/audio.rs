@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Length of each envelope window in seconds
+const WINDOW: f64 = 0.02;
+
+/// RMS energy of an audio track, one value per window
+#[derive(Debug, Clone, PartialEq)]
+pub struct Envelope {
+    /// Length of each window in seconds
+    pub window: f64,
+    /// Root mean square of the samples in each window, 0.0 to 1.0
+    pub rms: Vec<f32>,
+}
+
+impl Envelope {
+    /// Compute the envelope of mono samples in the range -1.0 to 1.0
+    ///
+    /// # Arguments
+    /// * `samples` - Mono audio samples
+    /// * `sample_rate` - Samples per second
+    pub fn from_samples(samples: &[f32], sample_rate: u32) -> Self {
+        let size = ((sample_rate as f64 * WINDOW).round() as usize).max(1);
+        let rms = samples
+            .chunks(size)
+            .map(|chunk| {
+                let sum: f32 = chunk.iter().map(|sample| sample * sample).sum();
+                (sum / chunk.len() as f32).sqrt()
+            })
+            .collect();
+        Self {
+            window: size as f64 / sample_rate as f64,
+            rms,
+        }
+    }
+
+    /// Compute the envelope of a 16-bit mono WAV file, as extracted for Whisper
+    pub fn from_wav(path: &Path) -> Result<Self> {
+        let reader = hound::WavReader::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let sample_rate = reader.spec().sample_rate;
+        let samples = reader
+            .into_samples::<i16>()
+            .map(|sample| sample.map(|sample| sample as f32 / 32768.0))
+            .collect::<Result<Vec<f32>, _>>()
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(Self::from_samples(&samples, sample_rate))
+    }
+
+    /// Combine with another track, keeping the louder of each window
+    pub fn loudest(mut self, other: &Envelope) -> Self {
+        if other.rms.len() > self.rms.len() {
+            self.rms.resize(other.rms.len(), 0.0);
+        }
+        for (rms, other) in self.rms.iter_mut().zip(&other.rms) {
+            *rms = rms.max(*other);
+        }
+        self
+    }
+
+    /// Find stretches quieter than `threshold` dBFS lasting at least `min_length` seconds
+    ///
+    /// Returns the start and end of each silence in seconds, ordered by time.
+    pub fn silences(&self, threshold: f64, min_length: f64) -> Vec<(f64, f64)> {
+        let level = 10f64.powf(threshold / 20.0) as f32;
+        let mut silences = Vec::new();
+        let mut start = None;
+        for i in 0..=self.rms.len() {
+            let quiet = self.rms.get(i).is_some_and(|&rms| rms < level);
+            match (quiet, start) {
+                (true, None) => start = Some(i),
+                (false, Some(first)) => {
+                    let (from, to) = (first as f64 * self.window, i as f64 * self.window);
+                    if to - from >= min_length {
+                        silences.push((from, to));
+                    }
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        silences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_silences() {
+        // 0.5s of tone, 0.3s of silence, 0.5s of tone, 0.04s of silence
+        let tone = |seconds: f64| {
+            (0..(seconds * 1000.0) as usize).map(|i| if i % 2 == 0 { 0.5 } else { -0.5 })
+        };
+        let samples: Vec<f32> = tone(0.5)
+            .chain(std::iter::repeat_n(0.0, 300))
+            .chain(tone(0.5))
+            .chain(std::iter::repeat_n(0.0, 40))
+            .collect();
+
+        let envelope = Envelope::from_samples(&samples, 1000);
+        assert_eq!(envelope.window, 0.02);
+        assert_eq!(envelope.rms[0], 0.5);
+
+        let silences = envelope.silences(-40.0, 0.1);
+        assert_eq!(silences.len(), 1);
+        assert!((silences[0].0 - 0.5).abs() < 1e-9);
+        assert!((silences[0].1 - 0.8).abs() < 1e-9);
+        assert_eq!(envelope.silences(-40.0, 0.02).len(), 2);
+    }
+
+    #[test]
+    fn test_loudest() {
+        let quiet = Envelope::from_samples(&[0.0; 40], 1000);
+        let loud = Envelope::from_samples(&[0.25; 60], 1000);
+        let combined = quiet.loudest(&loud);
+        assert_eq!(combined.rms, vec![0.25, 0.25, 0.25]);
+    }
+}
@@ -1,7 +1,13 @@
 use anyhow::{Context, Result};
+use log::{debug, warn};
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use super::config::Task;
+use super::search::Hit;
 
 /// Represents a timestamp in the transcription
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -12,6 +18,9 @@ pub struct Timestamp {
     pub end: f64,
     /// The transcribed text
     pub text: String,
+    /// Index of the Whisper segment the text belongs to
+    #[serde(default)]
+    pub segment: usize,
 }
 
 /// Represents a clip with its timing information
@@ -21,11 +30,112 @@ pub struct Clip {
     pub start: f64,
     /// End time in seconds
     pub end: f64,
-    /// The keyword that triggered this clip
+    /// Keyword of the first hit in the clip, used to name it
     pub keyword: String,
+    /// Every keyword hit merged into this clip, ordered by time
+    #[serde(default)]
+    pub hits: Vec<Hit>,
+}
+
+impl Clip {
+    /// Distinct keywords of the hits, in order of appearance
+    pub fn keywords(&self) -> Vec<&str> {
+        let mut keywords: Vec<&str> = Vec::new();
+        for hit in &self.hits {
+            if !keywords.contains(&hit.keyword.as_str()) {
+                keywords.push(&hit.keyword);
+            }
+        }
+        keywords
+    }
+
+    /// Lowest similarity between a hit and its keyword, 1.0 for exact matches
+    pub fn score(&self) -> f64 {
+        self.hits.iter().map(|hit| hit.score).fold(1.0, f64::min)
+    }
+}
+
+/// A cached transcription along with the settings that produced it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Transcription {
+    /// Whisper model used for the transcription
+    pub model: String,
+    /// Language setting used for the transcription, a language code or "auto"
+    #[serde(default = "default_language")]
+    pub language: String,
+    /// Whether the speech was transcribed or translated to English
+    #[serde(default)]
+    pub task: Task,
+    /// Hash of the Whisper decoding parameters used, see `Config::decoding_key`
+    #[serde(default)]
+    pub decoding: String,
+    /// Audio tracks that were transcribed (1-based indexing)
+    pub tracks: Vec<u32>,
+    /// Language Whisper transcribed each track in, in the order of `tracks`
+    #[serde(default)]
+    pub detected_languages: Vec<String>,
+    /// Word-level timestamps, in English when translated
+    pub timestamps: Vec<Timestamp>,
+    /// Word-level timestamps in the spoken language, only kept when translated
+    #[serde(default)]
+    pub original: Vec<Timestamp>,
+}
+
+/// Transcriptions cached before the language setting existed were all English
+fn default_language() -> String {
+    String::from("en")
+}
+
+/// A manifest entry mapping a cache fingerprint back to its input file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CacheEntry {
+    /// Content fingerprint used as the cache key
+    pub fingerprint: String,
+    /// Absolute path of the input file when it was fingerprinted
+    pub path: PathBuf,
+    /// Size of the input file in bytes
+    pub size: u64,
+    /// Modification time of the input file (seconds since the Unix epoch)
+    pub modified: u64,
+}
+
+/// Number of bytes sampled from the start, middle and end of an input file
+const FINGERPRINT_SAMPLE_SIZE: u64 = 64 * 1024;
+
+/// 64-bit FNV-1a hash, stable across platforms and Rust versions
+struct Fnv64(u64);
+
+impl Fnv64 {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Hash text into 16 hex digits that stay the same across runs and platforms
+pub fn stable_hash(text: &str) -> String {
+    let mut hasher = Fnv64::new();
+    hasher.write(text.as_bytes());
+    format!("{:016x}", hasher.0)
+}
+
+/// Read a transcription file
+fn read_transcription(path: &Path) -> Result<Transcription> {
+    let json = fs::read_to_string(path).context("Failed to read transcription file")?;
+    serde_json::from_str(&json).context("Failed to parse transcription file")
 }
 
 /// Manages cache directories and intermediate files
+///
+/// Cache entries are keyed on a content fingerprint of the input file rather
+/// than its name, so files with the same name in different folders don't
+/// collide. A manifest maps fingerprints back to the original input paths.
 #[derive(Debug)]
 pub struct Cache {
     /// Base cache directory
@@ -38,6 +148,8 @@ pub struct Cache {
     transcription_dir: PathBuf,
     /// Directory for clip metadata
     clips_dir: PathBuf,
+    /// Manifest of fingerprinted input files
+    manifest_path: PathBuf,
 }
 
 impl Default for Cache {
@@ -57,6 +169,7 @@ impl Cache {
         let audio_dir = cache_dir.join("audio");
         let transcription_dir = cache_dir.join("transcriptions");
         let clips_dir = cache_dir.join("clips");
+        let manifest_path = cache_dir.join("manifest.json");
 
         Self {
             cache_dir,
@@ -64,6 +177,7 @@ impl Cache {
             audio_dir,
             transcription_dir,
             clips_dir,
+            manifest_path,
         }
     }
 
@@ -77,6 +191,11 @@ impl Cache {
         Ok(())
     }
 
+    /// Get the base cache directory
+    pub fn dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
     /// Get the path for a model file
     pub fn model_path(&self, model_name: &str) -> PathBuf {
         self.models_dir.join(format!("ggml-{}.bin", model_name))
@@ -87,51 +206,320 @@ impl Cache {
         self.model_path(model_name).exists()
     }
 
+    /// Compute the content fingerprint used to key cache entries for an input
+    ///
+    /// The fingerprint hashes the file size, modification time and bytes
+    /// sampled from the start, middle and end of the file. If the input no
+    /// longer exists, the most recent manifest entry for its path is used.
+    ///
+    /// # Arguments
+    /// * `input_path` - Path to the input video file
+    pub fn fingerprint(&self, input_path: &Path) -> Result<String> {
+        let path = std::path::absolute(input_path).context("Failed to resolve input path")?;
+
+        if !path.exists() {
+            return self
+                .entries()?
+                .into_iter()
+                .filter(|entry| entry.path == path)
+                .max_by_key(|entry| entry.modified)
+                .map(|entry| entry.fingerprint)
+                .with_context(|| format!("No cache entries found for {}", path.display()));
+        }
+
+        let metadata = fs::metadata(&path).context("Failed to read input file metadata")?;
+        let size = metadata.len();
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+
+        let mut hasher = Fnv64::new();
+        hasher.write(&size.to_le_bytes());
+        hasher.write(&modified.as_nanos().to_le_bytes());
+
+        let mut file = fs::File::open(&path).context("Failed to open input file")?;
+        let last_offset = size.saturating_sub(FINGERPRINT_SAMPLE_SIZE);
+        let mut buffer = Vec::new();
+        for offset in [0, last_offset / 2, last_offset] {
+            buffer.clear();
+            file.seek(SeekFrom::Start(offset))?;
+            (&mut file)
+                .take(FINGERPRINT_SAMPLE_SIZE)
+                .read_to_end(&mut buffer)
+                .context("Failed to read input file")?;
+            hasher.write(&buffer);
+        }
+
+        let fingerprint = format!("{:016x}", hasher.0);
+        self.record_entry(CacheEntry {
+            fingerprint: fingerprint.clone(),
+            path,
+            size,
+            modified: modified.as_secs(),
+        })?;
+
+        Ok(fingerprint)
+    }
+
+    /// List all fingerprinted inputs recorded in the manifest
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        if !self.manifest_path.exists() {
+            return Ok(Vec::new());
+        }
+        let json = fs::read_to_string(&self.manifest_path).context("Failed to read manifest")?;
+        match serde_json::from_str(&json) {
+            Ok(entries) => Ok(entries),
+            Err(e) => {
+                // The fingerprints still identify the cached files, only their paths are lost
+                warn!(
+                    "Ignoring unreadable cache manifest {}: {}",
+                    self.manifest_path.display(),
+                    e
+                );
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Add or replace a manifest entry
+    fn record_entry(&self, entry: CacheEntry) -> Result<()> {
+        let mut entries = self.entries()?;
+        if let Some(existing) = entries
+            .iter_mut()
+            .find(|e| e.fingerprint == entry.fingerprint)
+        {
+            if existing.path == entry.path {
+                return Ok(());
+            }
+            *existing = entry;
+        } else {
+            entries.push(entry);
+        }
+        self.write_entries(&entries)
+    }
+
+    /// Write the manifest to disk
+    ///
+    /// The manifest is written to a temporary file that replaces it in one
+    /// rename, so an interrupted write or a concurrent run never leaves it truncated.
+    fn write_entries(&self, entries: &[CacheEntry]) -> Result<()> {
+        let json = serde_json::to_string_pretty(entries).context("Failed to serialize manifest")?;
+        let mut file = tempfile::NamedTempFile::new_in(&self.cache_dir)
+            .context("Failed to create temporary manifest")?;
+        file.write_all(json.as_bytes())
+            .context("Failed to write manifest")?;
+        file.persist(&self.manifest_path)
+            .context("Failed to replace manifest")?;
+        Ok(())
+    }
+
     /// Get the path for an extracted audio file
-    pub fn audio_path(&self, input_path: &Path, track: u32) -> PathBuf {
-        let file_stem = input_path.file_stem().unwrap_or_default();
-        self.audio_dir.join(format!(
-            "{}_track_{}.wav",
-            file_stem.to_string_lossy(),
-            track
-        ))
+    pub fn audio_path(&self, input_path: &Path, track: u32) -> Result<PathBuf> {
+        let fingerprint = self.fingerprint(input_path)?;
+        Ok(self
+            .audio_dir
+            .join(format!("{}_track_{}.wav", fingerprint, track)))
     }
 
     /// Get the path for a transcription file
-    pub fn transcription_path(&self, input_path: &Path) -> PathBuf {
-        let file_stem = input_path.file_stem().unwrap_or_default();
-        self.transcription_dir
-            .join(format!("{}.json", file_stem.to_string_lossy()))
+    ///
+    /// # Arguments
+    /// * `input_path` - Path to the input video file
+    /// * `model` - Whisper model used for the transcription
+    /// * `language` - Language setting used for the transcription
+    /// * `task` - Whether the speech was transcribed or translated
+    /// * `tracks` - Audio tracks covered by the transcription
+    /// * `decoding` - Hash of the decoding parameters used for the transcription
+    pub fn transcription_path(
+        &self,
+        input_path: &Path,
+        model: &str,
+        language: &str,
+        task: Task,
+        tracks: &[u32],
+        decoding: &str,
+    ) -> Result<PathBuf> {
+        let prefix = self.transcription_prefix(input_path, model, language, task, tracks)?;
+        Ok(self
+            .transcription_dir
+            .join(format!("{}{}.json", prefix, decoding)))
+    }
+
+    /// File name prefix shared by the transcriptions of an input with any decoding parameters
+    fn transcription_prefix(
+        &self,
+        input_path: &Path,
+        model: &str,
+        language: &str,
+        task: Task,
+        tracks: &[u32],
+    ) -> Result<String> {
+        let fingerprint = self.fingerprint(input_path)?;
+        let tracks = tracks
+            .iter()
+            .map(|track| track.to_string())
+            .collect::<Vec<_>>()
+            .join("-");
+        Ok(format!(
+            "{}_{}_{}_{}_{}_",
+            fingerprint,
+            model,
+            language,
+            task.as_str(),
+            tracks
+        ))
     }
 
     /// Get the path for a clips metadata file
-    pub fn clips_path(&self, input_path: &Path) -> PathBuf {
-        let file_stem = input_path.file_stem().unwrap_or_default();
-        self.clips_dir
-            .join(format!("{}_clips.json", file_stem.to_string_lossy()))
+    pub fn clips_path(&self, input_path: &Path) -> Result<PathBuf> {
+        let fingerprint = self.fingerprint(input_path)?;
+        Ok(self.clips_dir.join(format!("{}_clips.json", fingerprint)))
     }
 
     /// Save transcription data to cache
-    pub fn save_transcription(&self, input_path: &Path, timestamps: Vec<Timestamp>) -> Result<()> {
-        let path = self.transcription_path(input_path);
-        let json = serde_json::to_string_pretty(&timestamps)
+    pub fn save_transcription(
+        &self,
+        input_path: &Path,
+        transcription: &Transcription,
+    ) -> Result<()> {
+        let path = self.transcription_path(
+            input_path,
+            &transcription.model,
+            &transcription.language,
+            transcription.task,
+            &transcription.tracks,
+            &transcription.decoding,
+        )?;
+        let json = serde_json::to_string_pretty(transcription)
             .context("Failed to serialize transcription")?;
         fs::write(&path, json).context("Failed to write transcription file")?;
         Ok(())
     }
 
     /// Load transcription data from cache
-    pub fn load_transcription(&self, input_path: &Path) -> Result<Vec<Timestamp>> {
-        let path = self.transcription_path(input_path);
-        let json = fs::read_to_string(&path).context("Failed to read transcription file")?;
-        let timestamps =
-            serde_json::from_str(&json).context("Failed to parse transcription file")?;
-        Ok(timestamps)
+    pub fn load_transcription(
+        &self,
+        input_path: &Path,
+        model: &str,
+        language: &str,
+        task: Task,
+        tracks: &[u32],
+        decoding: &str,
+    ) -> Result<Transcription> {
+        let path = self.transcription_path(input_path, model, language, task, tracks, decoding)?;
+        read_transcription(&path)
+    }
+
+    /// Look up a cached transcription that is still valid for the input
+    ///
+    /// A transcription is only reused when it was made from the same file
+    /// contents with the same model, language setting, task, audio tracks
+    /// and decoding parameters.
+    ///
+    /// # Arguments
+    /// * `input_path` - Path to the input video file
+    /// * `model` - Whisper model the transcription must have been made with
+    /// * `language` - Language setting the transcription must have been made with
+    /// * `task` - Whether the transcription must be transcribed or translated
+    /// * `tracks` - Audio tracks the transcription must cover
+    /// * `decoding` - Hash of the decoding parameters the transcription must have been made with
+    pub fn find_transcription(
+        &self,
+        input_path: &Path,
+        model: &str,
+        language: &str,
+        task: Task,
+        tracks: &[u32],
+        decoding: &str,
+    ) -> Option<Transcription> {
+        if !input_path.exists() {
+            return None;
+        }
+
+        match self.transcription_path(input_path, model, language, task, tracks, decoding) {
+            Ok(path) if path.exists() => {}
+            Ok(_) => return None,
+            Err(e) => {
+                debug!("Failed to look up cached transcription: {:#}", e);
+                return None;
+            }
+        }
+
+        match self.load_transcription(input_path, model, language, task, tracks, decoding) {
+            Ok(transcription)
+                if transcription.model == model
+                    && transcription.language == language
+                    && transcription.task == task
+                    && transcription.tracks == tracks
+                    && transcription.decoding == decoding =>
+            {
+                Some(transcription)
+            }
+            Ok(_) => {
+                debug!("Cached transcription was made with a different model, language, task, tracks or decoding");
+                None
+            }
+            Err(e) => {
+                debug!("Ignoring unreadable cached transcription: {:#}", e);
+                None
+            }
+        }
+    }
+
+    /// Look up the newest cached transcription made with other decoding parameters
+    ///
+    /// Used when only searching a transcription, where one made with
+    /// different decoding parameters is better than none.
+    ///
+    /// # Arguments
+    /// * `input_path` - Path to the input video file
+    /// * `model` - Whisper model the transcription must have been made with
+    /// * `language` - Language setting the transcription must have been made with
+    /// * `task` - Whether the transcription must be transcribed or translated
+    /// * `tracks` - Audio tracks the transcription must cover
+    pub fn find_transcription_any_decoding(
+        &self,
+        input_path: &Path,
+        model: &str,
+        language: &str,
+        task: Task,
+        tracks: &[u32],
+    ) -> Option<Transcription> {
+        if !input_path.exists() || !self.transcription_dir.exists() {
+            return None;
+        }
+        let prefix = self
+            .transcription_prefix(input_path, model, language, task, tracks)
+            .ok()?;
+
+        let newest = fs::read_dir(&self.transcription_dir)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+            .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok())?;
+
+        match read_transcription(&newest.path()) {
+            Ok(transcription)
+                if transcription.model == model
+                    && transcription.language == language
+                    && transcription.task == task
+                    && transcription.tracks == tracks =>
+            {
+                Some(transcription)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                debug!("Ignoring unreadable cached transcription: {:#}", e);
+                None
+            }
+        }
     }
 
     /// Save clips metadata to cache
     pub fn save_clips(&self, input_path: &Path, clips: Vec<Clip>) -> Result<()> {
-        let path = self.clips_path(input_path);
+        let path = self.clips_path(input_path)?;
         let json = serde_json::to_string_pretty(&clips).context("Failed to serialize clips")?;
         fs::write(&path, json).context("Failed to write clips file")?;
         Ok(())
@@ -139,12 +527,44 @@ impl Cache {
 
     /// Load clips metadata from cache
     pub fn load_clips(&self, input_path: &Path) -> Result<Vec<Clip>> {
-        let path = self.clips_path(input_path);
+        let path = self.clips_path(input_path)?;
         let json = fs::read_to_string(&path).context("Failed to read clips file")?;
         let clips = serde_json::from_str(&json).context("Failed to parse clips file")?;
         Ok(clips)
     }
 
+    /// List the cached files stored under a fingerprint
+    pub fn entry_files(&self, fingerprint: &str) -> Result<Vec<PathBuf>> {
+        self.files_in(
+            &[&self.audio_dir, &self.transcription_dir, &self.clips_dir],
+            fingerprint,
+        )
+    }
+
+    /// List the files stored under a fingerprint in the given cache directories
+    fn files_in(&self, dirs: &[&PathBuf], fingerprint: &str) -> Result<Vec<PathBuf>> {
+        let prefix = format!("{}_", fingerprint);
+        let mut files = Vec::new();
+
+        for dir in dirs {
+            if !dir.exists() {
+                continue;
+            }
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().starts_with(&prefix))
+                    .unwrap_or(false)
+                {
+                    files.push(path);
+                }
+            }
+        }
+
+        Ok(files)
+    }
+
     /// Clean up all cache files
     pub fn cleanup(&self) -> Result<()> {
         if self.cache_dir.exists() {
@@ -153,36 +573,66 @@ impl Cache {
         Ok(())
     }
 
+    /// Clean up all intermediate files, keeping downloaded models
+    pub fn clear(&self) -> Result<()> {
+        for dir in [&self.audio_dir, &self.transcription_dir, &self.clips_dir] {
+            if dir.exists() {
+                fs::remove_dir_all(dir).context("Failed to remove cache directory")?;
+            }
+        }
+        if self.manifest_path.exists() {
+            fs::remove_file(&self.manifest_path).context("Failed to remove manifest")?;
+        }
+        self.init()
+    }
+
     /// Clean up cache files for a specific input file
+    ///
+    /// Removes the entries for the input's current fingerprint as well as any
+    /// older fingerprints recorded for the same path.
     pub fn cleanup_for_input(&self, input_path: &Path) -> Result<()> {
-        // Remove audio files
-        for entry in fs::read_dir(&self.audio_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .map(|s| s.starts_with(input_path.file_stem().unwrap().to_string_lossy().as_ref()))
-                .unwrap_or(false)
-            {
-                fs::remove_file(path)?;
+        let mut entries = self.entries()?;
+        let fingerprints = self.input_fingerprints(input_path, &entries)?;
+
+        for fingerprint in &fingerprints {
+            for file in self.entry_files(fingerprint)? {
+                fs::remove_file(file)?;
             }
         }
 
-        // Remove transcription file
-        let transcription_path = self.transcription_path(input_path);
-        if transcription_path.exists() {
-            fs::remove_file(transcription_path)?;
-        }
+        entries.retain(|entry| !fingerprints.contains(&entry.fingerprint));
+        self.write_entries(&entries)?;
 
-        // Remove clips file
-        let clips_path = self.clips_path(input_path);
-        if clips_path.exists() {
-            fs::remove_file(clips_path)?;
-        }
+        Ok(())
+    }
 
+    /// Clean up the extracted audio and clips metadata for a specific input
+    ///
+    /// Transcriptions are kept so the next run on the same input skips
+    /// Whisper, they are only removed by `cleanup_for_input` or `clear`.
+    pub fn cleanup_intermediate(&self, input_path: &Path) -> Result<()> {
+        let entries = self.entries()?;
+        for fingerprint in self.input_fingerprints(input_path, &entries)? {
+            for file in self.files_in(&[&self.audio_dir, &self.clips_dir], &fingerprint)? {
+                fs::remove_file(file)?;
+            }
+        }
         Ok(())
     }
+
+    /// The current fingerprint of an input and any older ones recorded for its path
+    fn input_fingerprints(&self, input_path: &Path, entries: &[CacheEntry]) -> Result<Vec<String>> {
+        let path = std::path::absolute(input_path).context("Failed to resolve input path")?;
+        let mut fingerprints: Vec<String> = entries
+            .iter()
+            .filter(|entry| entry.path == path)
+            .map(|entry| entry.fingerprint.clone())
+            .collect();
+        if path.exists() {
+            fingerprints.push(self.fingerprint(&path)?);
+        }
+        Ok(fingerprints)
+    }
 }
 
 #[cfg(test)]
@@ -216,65 +666,282 @@ mod tests {
         );
     }
 
+    fn create_input(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
     #[test]
     fn test_save_and_load_transcription() -> Result<()> {
-        let (cache, _temp_dir) = setup_test_cache();
-        let input_path = Path::new("test.mp4");
+        let (cache, temp_dir) = setup_test_cache();
+        let input_path = create_input(temp_dir.path(), "test.mp4", "video");
+
+        let transcription = Transcription {
+            model: "base".to_string(),
+            language: "auto".to_string(),
+            task: Task::Translate,
+            decoding: "greedy".to_string(),
+            tracks: vec![1],
+            detected_languages: vec!["es".to_string()],
+            timestamps: vec![Timestamp {
+                start: 0.0,
+                end: 1.0,
+                text: "Hello".to_string(),
+                segment: 0,
+            }],
+            original: vec![Timestamp {
+                start: 0.0,
+                end: 1.0,
+                text: "Hola".to_string(),
+                segment: 0,
+            }],
+        };
+
+        cache.save_transcription(&input_path, &transcription)?;
+        let loaded = cache.load_transcription(
+            &input_path,
+            "base",
+            "auto",
+            Task::Translate,
+            &[1],
+            "greedy",
+        )?;
+
+        assert_eq!(loaded.model, "base");
+        assert_eq!(loaded.detected_languages, vec!["es"]);
+        assert_eq!(loaded.timestamps.len(), 1);
+        assert_eq!(loaded.timestamps[0].text, "Hello");
+        assert_eq!(loaded.original[0].text, "Hola");
 
-        let timestamps = vec![Timestamp {
-            start: 0.0,
-            end: 1.0,
-            text: "Hello".to_string(),
-        }];
+        Ok(())
+    }
 
-        cache.save_transcription(input_path, timestamps.clone())?;
-        let loaded = cache.load_transcription(input_path)?;
+    #[test]
+    fn test_find_transcription() -> Result<()> {
+        let (cache, temp_dir) = setup_test_cache();
+        let input_path = create_input(temp_dir.path(), "test.mp4", "video");
+
+        assert!(cache
+            .find_transcription(&input_path, "base", "en", Task::Transcribe, &[1], "greedy")
+            .is_none());
+
+        let transcription = Transcription {
+            model: "base".to_string(),
+            language: "en".to_string(),
+            task: Task::Transcribe,
+            decoding: "greedy".to_string(),
+            tracks: vec![1],
+            detected_languages: vec!["en".to_string()],
+            timestamps: Vec::new(),
+            original: Vec::new(),
+        };
+        cache.save_transcription(&input_path, &transcription)?;
+
+        assert!(cache
+            .find_transcription(&input_path, "base", "en", Task::Transcribe, &[1], "greedy")
+            .is_some());
+        assert!(cache
+            .find_transcription(&input_path, "small", "en", Task::Transcribe, &[1], "greedy")
+            .is_none());
+        assert!(cache
+            .find_transcription(
+                &input_path,
+                "base",
+                "auto",
+                Task::Transcribe,
+                &[1],
+                "greedy"
+            )
+            .is_none());
+        assert!(cache
+            .find_transcription(&input_path, "base", "en", Task::Translate, &[1], "greedy")
+            .is_none());
+        assert!(cache
+            .find_transcription(
+                &input_path,
+                "base",
+                "en",
+                Task::Transcribe,
+                &[1, 2],
+                "greedy"
+            )
+            .is_none());
+
+        // Other decoding parameters are a miss, unless any decoding will do
+        assert!(cache
+            .find_transcription(&input_path, "base", "en", Task::Transcribe, &[1], "beam")
+            .is_none());
+        let found = cache
+            .find_transcription_any_decoding(&input_path, "base", "en", Task::Transcribe, &[1])
+            .unwrap();
+        assert_eq!(found.decoding, "greedy");
+        assert!(cache
+            .find_transcription_any_decoding(&input_path, "base", "en", Task::Transcribe, &[1, 2])
+            .is_none());
 
-        assert_eq!(loaded.len(), 1);
-        assert_eq!(loaded[0].text, "Hello");
+        Ok(())
+    }
+
+    #[test]
+    fn test_same_file_name_with_different_contents() -> Result<()> {
+        let (cache, temp_dir) = setup_test_cache();
+        let first = create_input(temp_dir.path(), "a/stream.mp4", "first video");
+        let second = create_input(temp_dir.path(), "b/stream.mp4", "second video");
+
+        assert_ne!(cache.fingerprint(&first)?, cache.fingerprint(&second)?);
+        assert_ne!(cache.audio_path(&first, 1)?, cache.audio_path(&second, 1)?);
+        assert_eq!(cache.entries()?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_file_name() -> Result<()> {
+        let (cache, temp_dir) = setup_test_cache();
+        let first = create_input(temp_dir.path(), "a/stream.mp4", "same video");
+        let second = create_input(temp_dir.path(), "b/renamed.mkv", "same video");
+
+        // A copy with the same modification time, as `cp -p` or a sync tool leaves it
+        let modified = fs::metadata(&first)?.modified()?;
+        fs::File::options()
+            .write(true)
+            .open(&second)?
+            .set_modified(modified)?;
+
+        assert_eq!(cache.fingerprint(&first)?, cache.fingerprint(&second)?);
+        assert_eq!(cache.audio_path(&first, 1)?, cache.audio_path(&second, 1)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupt_manifest_is_ignored() -> Result<()> {
+        let (cache, temp_dir) = setup_test_cache();
+        let input_path = create_input(temp_dir.path(), "test.mp4", "video");
+
+        fs::write(&cache.manifest_path, "[{\"fingerprint\": \"trunc")?;
+        assert!(cache.entries()?.is_empty());
+
+        // The next lookup replaces it with a readable manifest
+        let fingerprint = cache.fingerprint(&input_path)?;
+        let entries = cache.entries()?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].fingerprint, fingerprint);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint_falls_back_to_manifest() -> Result<()> {
+        let (cache, temp_dir) = setup_test_cache();
+        let input_path = create_input(temp_dir.path(), "test.mp4", "video");
+
+        let fingerprint = cache.fingerprint(&input_path)?;
+        fs::remove_file(&input_path)?;
+        assert_eq!(cache.fingerprint(&input_path)?, fingerprint);
 
         Ok(())
     }
 
     #[test]
     fn test_save_and_load_clips() -> Result<()> {
-        let (cache, _temp_dir) = setup_test_cache();
-        let input_path = Path::new("test.mp4");
+        let (cache, temp_dir) = setup_test_cache();
+        let input_path = create_input(temp_dir.path(), "test.mp4", "video");
 
         let clips = vec![Clip {
             start: 0.0,
             end: 1.0,
             keyword: "test".to_string(),
+            hits: vec![Hit {
+                keyword: "test".to_string(),
+                start: 0.4,
+                end: 0.6,
+                text: "Test".to_string(),
+                score: 1.0,
+            }],
         }];
 
-        cache.save_clips(input_path, clips.clone())?;
-        let loaded = cache.load_clips(input_path)?;
+        cache.save_clips(&input_path, clips.clone())?;
+        let loaded = cache.load_clips(&input_path)?;
 
         assert_eq!(loaded.len(), 1);
         assert_eq!(loaded[0].keyword, "test");
+        assert_eq!(loaded[0].hits, clips[0].hits);
 
         Ok(())
     }
 
     #[test]
     fn test_cleanup() -> Result<()> {
-        let (cache, _temp_dir) = setup_test_cache();
+        let (cache, temp_dir) = setup_test_cache();
 
         // Create some test files
-        let input_path = Path::new("test.mp4");
-        fs::write(cache.audio_path(input_path, 1), "test")?;
+        let input_path = create_input(temp_dir.path(), "vod.mp4", "video");
+        let other_path = create_input(temp_dir.path(), "vod2.mp4", "other video");
+        let audio_path = cache.audio_path(&input_path, 1)?;
+        let other_audio_path = cache.audio_path(&other_path, 1)?;
+        fs::write(&audio_path, "test")?;
+        fs::write(&other_audio_path, "test")?;
 
         let clips = vec![Clip {
             start: 0.0,
             end: 1.0,
             keyword: "test".to_string(),
+            hits: vec![Hit {
+                keyword: "test".to_string(),
+                start: 0.4,
+                end: 0.6,
+                text: "Test".to_string(),
+                score: 1.0,
+            }],
         }];
-        cache.save_clips(input_path, clips)?;
+        cache.save_clips(&input_path, clips)?;
+        let clips_path = cache.clips_path(&input_path)?;
+
+        // Cleaning up after a run keeps the transcription for the next run
+        let transcription = Transcription {
+            model: "base".to_string(),
+            language: "en".to_string(),
+            task: Task::Transcribe,
+            decoding: "greedy".to_string(),
+            tracks: vec![1],
+            detected_languages: vec!["en".to_string()],
+            timestamps: Vec::new(),
+            original: Vec::new(),
+        };
+        cache.save_transcription(&input_path, &transcription)?;
+        let transcription_path = cache.transcription_path(
+            &input_path,
+            "base",
+            "en",
+            Task::Transcribe,
+            &[1],
+            "greedy",
+        )?;
+        cache.cleanup_intermediate(&input_path)?;
+        assert!(!audio_path.exists());
+        assert!(!clips_path.exists());
+        assert!(cache
+            .find_transcription(&input_path, "base", "en", Task::Transcribe, &[1], "greedy")
+            .is_some());
 
         // Test cleanup for specific input
-        cache.cleanup_for_input(input_path)?;
-        assert!(!cache.audio_path(input_path, 1).exists());
-        assert!(!cache.clips_path(input_path).exists());
+        fs::write(&audio_path, "test")?;
+        cache.cleanup_for_input(&input_path)?;
+        assert!(!transcription_path.exists());
+        assert!(!audio_path.exists());
+        assert!(!clips_path.exists());
+        assert!(other_audio_path.exists());
+        assert_eq!(cache.entries()?.len(), 1);
+
+        // Test clearing intermediate files
+        fs::write(cache.model_path("base"), "model")?;
+        cache.clear()?;
+        assert!(!other_audio_path.exists());
+        assert!(cache.entries()?.is_empty());
+        assert!(cache.model_exists("base"));
 
         // Test full cleanup
         cache.cleanup()?;
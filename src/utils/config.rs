@@ -1,16 +1,143 @@
 use anyhow::{Context, Result};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use whisper_rs::SamplingStrategy;
+
+use super::cache::stable_hash;
+use super::desilence::DesilenceOutput;
+use super::export::ExportFormat;
+use super::markers::MarkerFormat;
+use super::query::Query;
+use super::timeline::TimelineFormat;
 
 /// Represents a clip configuration with start and end times
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ClipConfig {
     /// Seconds before the keyword to start the clip
-    pub start_time: u32,
+    pub start_time: f64,
     /// Seconds after the keyword to end the clip
-    pub end_time: u32,
+    pub end_time: f64,
+    /// Shortest clip for this keyword in seconds, overrides `[limits]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_duration: Option<f64>,
+    /// Longest clip containing this keyword in seconds, overrides `[limits]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duration: Option<f64>,
+    /// Hits with a higher priority are kept first when `[limits] overflow = "drop"`
+    #[serde(default)]
+    pub priority: i32,
+    /// Let a multi-word keyword match across Whisper segment boundaries
+    #[serde(default)]
+    pub cross_segments: bool,
+    /// How spoken words are compared with the keyword and its aliases
+    #[serde(default)]
+    pub match_mode: MatchMode,
+    /// Largest edit distance accepted per word in fuzzy mode
+    #[serde(default = "default_max_distance")]
+    pub max_distance: usize,
+    /// Other spellings or phrases that count as the keyword
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Regular expression matched against each spoken word instead of the table key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Glob (`*` and `?`) matched against each spoken word instead of the table key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glob: Option<String>,
+    /// `pattern` or `glob` compiled by `compile`
+    #[serde(skip)]
+    pub matcher: Option<Regex>,
+    /// Boolean or proximity query matched instead of the table key,
+    /// e.g. `"boss" AND "dead" WITHIN 10s`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    /// `query` parsed by `compile`
+    #[serde(skip)]
+    pub query_expression: Option<Query>,
+}
+
+impl Default for ClipConfig {
+    fn default() -> Self {
+        Self {
+            start_time: 0.0,
+            end_time: 0.0,
+            min_duration: None,
+            max_duration: None,
+            priority: 0,
+            cross_segments: false,
+            match_mode: MatchMode::default(),
+            max_distance: default_max_distance(),
+            aliases: Vec::new(),
+            pattern: None,
+            glob: None,
+            matcher: None,
+            query: None,
+            query_expression: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// Words must be spelled exactly like the keyword, ignoring case and punctuation
+    #[default]
+    Exact,
+    /// Words may be up to `max_distance` edits away from the keyword
+    Fuzzy,
+    /// Words must have the same American Soundex code as the keyword
+    Phonetic,
+}
+
+impl ClipConfig {
+    /// Compile `pattern` or `glob` into a case-insensitive whole-word matcher
+    /// and parse `query`
+    ///
+    /// # Arguments
+    /// * `name` - Table key of the clip, used in error messages
+    pub fn compile(&mut self, name: &str) -> Result<()> {
+        self.query_expression = match &self.query {
+            Some(_) if self.pattern.is_some() || self.glob.is_some() => {
+                anyhow::bail!("clips.{} can't have both a query and a pattern", name)
+            }
+            Some(query) => Some(
+                Query::parse(query).with_context(|| format!("Invalid query for clips.{}", name))?,
+            ),
+            None => None,
+        };
+
+        let pattern = match (&self.pattern, &self.glob) {
+            (Some(_), Some(_)) => {
+                anyhow::bail!("clips.{} can't have both a pattern and a glob", name)
+            }
+            (Some(pattern), None) => pattern.clone(),
+            (None, Some(glob)) => glob_to_regex(glob),
+            (None, None) => {
+                self.matcher = None;
+                return Ok(());
+            }
+        };
+
+        let matcher = Regex::new(&format!("(?i)^(?:{})$", pattern))
+            .with_context(|| format!("Invalid pattern for clips.{}", name))?;
+        self.matcher = Some(matcher);
+        Ok(())
+    }
+}
+
+/// Translate a glob into a regular expression, `*` matching any run of
+/// characters and `?` a single character
+fn glob_to_regex(glob: &str) -> String {
+    glob.chars()
+        .map(|c| match c {
+            '*' => String::from(".*"),
+            '?' => String::from("."),
+            c => regex::escape(&c.to_string()),
+        })
+        .collect()
 }
 
 /// Main configuration structure for the Clive application
@@ -24,15 +151,266 @@ pub struct Config {
     pub clips: HashMap<String, ClipConfig>,
     /// Output configuration
     pub output: OutputConfig,
+    /// Transcript export configuration
+    #[serde(default)]
+    pub export: ExportConfig,
+    /// Burned-in caption configuration
+    #[serde(default)]
+    pub captions: CaptionsConfig,
+    /// Clip length limits applied to every keyword
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    /// How clips close to each other are merged
+    #[serde(default)]
+    pub merge: MergeConfig,
+    /// Snapping of clip boundaries to natural edges in the speech
+    #[serde(default)]
+    pub snap: SnapConfig,
+    /// Silence detection for `clive desilence`
+    #[serde(default)]
+    pub desilence: DesilenceConfig,
+    /// Whisper decoding parameters
+    #[serde(default)]
+    pub whisper: WhisperConfig,
     /// Input file path (from CLI)
     #[serde(skip)]
     pub input_file: Option<PathBuf>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct LimitsConfig {
+    /// Shortest clip in seconds, short clips grow evenly around their hit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_duration: Option<f64>,
+    /// Longest clip in seconds, merged clips past this are split or thinned out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duration: Option<f64>,
+    /// What to do when merged clips grow past `max_duration`
+    #[serde(default)]
+    pub overflow: Overflow,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Overflow {
+    /// Split the merged clip into several clips that each fit
+    #[default]
+    Split,
+    /// Keep one clip and drop the lowest-priority hits until it fits
+    Drop,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct MergeConfig {
+    /// Which clips may be merged with each other
+    #[serde(default)]
+    pub mode: MergeMode,
+    /// Also merge clips less than this many seconds apart
+    #[serde(default)]
+    pub gap: f64,
+    /// Most hits a merged clip can hold, extra hits follow `[limits] overflow`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_hits: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeMode {
+    /// Merge clips of any keywords
+    #[default]
+    Global,
+    /// Only merge clips of the same keyword
+    Keyword,
+    /// Keep one clip per hit, even when they overlap
+    Never,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapConfig {
+    /// Edges to move clip boundaries to
+    #[serde(default)]
+    pub mode: SnapMode,
+    /// Furthest a boundary may move, in seconds
+    #[serde(default = "default_snap_tolerance")]
+    pub tolerance: f64,
+    /// Audio quieter than this many dBFS counts as silence
+    #[serde(default = "default_silence_threshold")]
+    pub silence_threshold: f64,
+    /// Shortest pause in seconds that counts as silence
+    #[serde(default = "default_min_silence")]
+    pub min_silence: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapMode {
+    /// Keep the padded boundaries
+    #[default]
+    None,
+    /// Start and end of Whisper segments
+    Segment,
+    /// Sentence-ending punctuation in the transcript
+    Sentence,
+    /// Pauses found in the energy of the extracted audio
+    Silence,
+}
+
+impl Default for SnapConfig {
+    fn default() -> Self {
+        Self {
+            mode: SnapMode::default(),
+            tolerance: default_snap_tolerance(),
+            silence_threshold: default_silence_threshold(),
+            min_silence: default_min_silence(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DesilenceConfig {
+    /// Audio quieter than this many dBFS counts as silence
+    #[serde(default = "default_silence_threshold")]
+    pub threshold: f64,
+    /// Shortest silence in seconds that gets cut
+    #[serde(default = "default_desilence_min_silence")]
+    pub min_silence: f64,
+    /// Seconds of silence kept next to the speech on either side of a cut
+    #[serde(default = "default_desilence_padding")]
+    pub padding: f64,
+    /// Write a tightened video or an EDL of the kept ranges
+    #[serde(default)]
+    pub output: DesilenceOutput,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WhisperConfig {
+    /// How Whisper picks the next token
+    #[serde(default)]
+    pub strategy: WhisperStrategy,
+    /// Number of beams kept by beam search
+    #[serde(default = "default_beam_size")]
+    pub beam_size: u32,
+    /// Number of candidates greedy decoding samples when the temperature is above 0
+    #[serde(default = "default_best_of")]
+    pub best_of: u32,
+    /// Sampling temperature, 0 always picks the most likely token
+    #[serde(default)]
+    pub temperature: f32,
+    /// Temperature added when decoding a segment fails the thresholds, 0 disables the fallback
+    #[serde(default = "default_temperature_inc")]
+    pub temperature_inc: f32,
+    /// Threads used by Whisper, defaults to up to 4 of the available cores
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threads: Option<u32>,
+    /// Segments more likely than this to contain no speech are skipped
+    #[serde(default = "default_no_speech_threshold")]
+    pub no_speech_threshold: f32,
+    /// Segments with a lower average log probability are decoded again at a higher temperature
+    #[serde(default = "default_logprob_threshold")]
+    pub logprob_threshold: f32,
+    /// Stop Whisper from starting a segment with a blank
+    #[serde(default = "default_suppress_blank")]
+    pub suppress_blank: bool,
+    /// Longest segment in characters, 0 for no limit, segments are split between words
+    #[serde(default)]
+    pub max_len: u32,
+    /// Text Whisper reads as if it came right before the audio, to set the style and topic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_prompt: Option<String>,
+    /// Names and jargon Whisper should spell correctly, added to the prompt
+    #[serde(default)]
+    pub vocabulary: Vec<String>,
+    /// Also add the `[clips]` keywords and aliases to the prompt
+    #[serde(default = "default_seed_keywords")]
+    pub seed_keywords: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WhisperStrategy {
+    /// Pick the most likely token, fast
+    #[default]
+    Greedy,
+    /// Keep several candidate transcripts, slower but more accurate
+    Beam,
+}
+
+impl WhisperConfig {
+    /// The sampling strategy to run Whisper with
+    pub fn sampling_strategy(&self) -> SamplingStrategy {
+        match self.strategy {
+            WhisperStrategy::Greedy => SamplingStrategy::Greedy {
+                best_of: self.best_of as i32,
+            },
+            WhisperStrategy::Beam => SamplingStrategy::BeamSearch {
+                beam_size: self.beam_size as i32,
+                patience: -1.0,
+            },
+        }
+    }
+}
+
+impl Default for WhisperConfig {
+    fn default() -> Self {
+        Self {
+            strategy: WhisperStrategy::default(),
+            beam_size: default_beam_size(),
+            best_of: default_best_of(),
+            temperature: 0.0,
+            temperature_inc: default_temperature_inc(),
+            threads: None,
+            no_speech_threshold: default_no_speech_threshold(),
+            logprob_threshold: default_logprob_threshold(),
+            suppress_blank: default_suppress_blank(),
+            max_len: 0,
+            initial_prompt: None,
+            vocabulary: Vec::new(),
+            seed_keywords: default_seed_keywords(),
+        }
+    }
+}
+
+impl Default for DesilenceConfig {
+    fn default() -> Self {
+        Self {
+            threshold: default_silence_threshold(),
+            min_silence: default_desilence_min_silence(),
+            padding: default_desilence_padding(),
+            output: DesilenceOutput::default(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CliveConfig {
     /// Whisper model to use (base, tiny, small, medium, large)
     pub model: String,
+    /// Spoken language as an ISO 639-1 code like "es", or "auto" to let Whisper detect it
+    #[serde(default = "default_language")]
+    pub language: String,
+    /// Whether Whisper transcribes the speech or translates it to English
+    #[serde(default)]
+    pub task: Task,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Task {
+    /// Transcribe in the spoken language
+    #[default]
+    Transcribe,
+    /// Translate to English, keeping the original transcript alongside
+    Translate,
+}
+
+impl Task {
+    /// Name of the task as written in the config
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Task::Transcribe => "transcribe",
+            Task::Translate => "translate",
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -47,6 +425,320 @@ pub struct OutputConfig {
     /// Directory where output files will be saved
     #[serde(default = "default_output_dir")]
     pub directory: PathBuf,
+    /// Subtitle sidecar formats written next to each clip (srt, vtt)
+    #[serde(default)]
+    pub subtitles: Vec<ExportFormat>,
+    /// How clips are cut from the input
+    #[serde(default)]
+    pub cut_mode: CutMode,
+    /// Video encoder used when clips are re-encoded
+    #[serde(default = "default_codec")]
+    pub codec: String,
+    /// Constant rate factor used when clips are re-encoded
+    #[serde(default = "default_crf")]
+    pub crf: u32,
+    /// Encoder preset used when clips are re-encoded
+    #[serde(default = "default_preset")]
+    pub preset: String,
+    /// Cut each clip into its own video file
+    #[serde(default = "default_cut_clips")]
+    pub cut_clips: bool,
+    /// Edit decision lists of the clips written to the output directory (edl, fcpxml, otio)
+    #[serde(default)]
+    pub timelines: Vec<TimelineFormat>,
+    /// Marker lists of the raw keyword hits written to the output directory
+    /// (resolve, premiere, chapters)
+    #[serde(default)]
+    pub markers: Vec<MarkerFormat>,
+    /// Concatenate every clip into a single supercut video
+    #[serde(default)]
+    pub supercut: bool,
+    /// Order of the clips in the supercut
+    #[serde(default)]
+    pub supercut_order: SupercutOrder,
+    /// Insert a title card showing the keyword before each clip or keyword group
+    #[serde(default)]
+    pub title_cards: bool,
+    /// How long each title card is shown, in seconds
+    #[serde(default = "default_title_card_duration")]
+    pub title_card_duration: f64,
+    /// Crossfade between supercut clips in seconds, 0 for hard cuts
+    #[serde(default)]
+    pub crossfade: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SupercutOrder {
+    /// In the order the clips appear in the input
+    #[default]
+    Timeline,
+    /// Grouped by keyword, each group in timeline order
+    Keyword,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CutMode {
+    /// Stream copy, fast but snaps to keyframes
+    #[default]
+    Copy,
+    /// Re-encode the whole clip for frame-accurate cuts
+    Accurate,
+    /// Re-encode up to the first keyframe and stream copy the rest
+    Smart,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportConfig {
+    /// Transcript formats written to the output directory
+    #[serde(default)]
+    pub formats: Vec<ExportFormat>,
+    /// Maximum number of characters per subtitle line
+    #[serde(default = "default_max_line_length")]
+    pub max_line_length: usize,
+    /// Maximum number of lines per subtitle cue
+    #[serde(default = "default_max_lines")]
+    pub max_lines: usize,
+    /// Maximum duration of a subtitle cue in seconds
+    #[serde(default = "default_max_cue_duration")]
+    pub max_cue_duration: f64,
+    /// Show the original-language text below translated subtitles
+    #[serde(default)]
+    pub bilingual: bool,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            formats: Vec::new(),
+            max_line_length: default_max_line_length(),
+            max_lines: default_max_lines(),
+            max_cue_duration: default_max_cue_duration(),
+            bilingual: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CaptionsConfig {
+    /// Burn captions into every clip
+    #[serde(default)]
+    pub enabled: bool,
+    /// Font family used for the captions
+    #[serde(default = "default_caption_font")]
+    pub font: String,
+    /// Font size in pixels
+    #[serde(default = "default_caption_font_size")]
+    pub font_size: u32,
+    /// Vertical position of the captions
+    #[serde(default)]
+    pub position: CaptionPosition,
+    /// Distance in pixels from the top or bottom edge of the frame
+    #[serde(default = "default_caption_margin")]
+    pub margin: u32,
+    /// Text color as #RRGGBB
+    #[serde(default = "default_caption_color")]
+    pub color: String,
+    /// Outline color as #RRGGBB
+    #[serde(default = "default_caption_outline_color")]
+    pub outline_color: String,
+    /// Outline width in pixels
+    #[serde(default = "default_caption_outline")]
+    pub outline: f64,
+    /// How the word currently being spoken is highlighted
+    #[serde(default)]
+    pub highlight: CaptionHighlight,
+    /// Color of the highlighted word as #RRGGBB
+    #[serde(default = "default_caption_highlight_color")]
+    pub highlight_color: String,
+    /// Maximum number of characters per caption line
+    #[serde(default = "default_caption_line_length")]
+    pub max_line_length: usize,
+    /// Maximum number of lines shown at once
+    #[serde(default = "default_caption_lines")]
+    pub max_lines: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptionPosition {
+    Top,
+    Center,
+    #[default]
+    Bottom,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptionHighlight {
+    /// Show whole lines without highlighting
+    #[default]
+    None,
+    /// Highlight each word as it is spoken
+    Karaoke,
+}
+
+impl Default for CaptionsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            font: default_caption_font(),
+            font_size: default_caption_font_size(),
+            position: CaptionPosition::default(),
+            margin: default_caption_margin(),
+            color: default_caption_color(),
+            outline_color: default_caption_outline_color(),
+            outline: default_caption_outline(),
+            highlight: CaptionHighlight::default(),
+            highlight_color: default_caption_highlight_color(),
+            max_line_length: default_caption_line_length(),
+            max_lines: default_caption_lines(),
+        }
+    }
+}
+
+/// Check that a pair of clip duration limits is usable
+fn check_durations(section: &str, min: Option<f64>, max: Option<f64>) -> Result<()> {
+    if min.is_some_and(|min| min < 0.0) {
+        anyhow::bail!("{} min_duration must not be negative", section);
+    }
+    if max.is_some_and(|max| max <= 0.0) {
+        anyhow::bail!("{} max_duration must be greater than 0", section);
+    }
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            anyhow::bail!(
+                "{} min_duration must not be longer than max_duration",
+                section
+            );
+        }
+    }
+    Ok(())
+}
+
+fn default_language() -> String {
+    String::from("en")
+}
+
+fn default_snap_tolerance() -> f64 {
+    2.0
+}
+
+fn default_silence_threshold() -> f64 {
+    -40.0
+}
+
+fn default_min_silence() -> f64 {
+    0.15
+}
+
+fn default_desilence_min_silence() -> f64 {
+    0.5
+}
+
+fn default_desilence_padding() -> f64 {
+    0.15
+}
+
+fn default_beam_size() -> u32 {
+    5
+}
+
+fn default_best_of() -> u32 {
+    1
+}
+
+fn default_temperature_inc() -> f32 {
+    0.2
+}
+
+fn default_no_speech_threshold() -> f32 {
+    0.6
+}
+
+fn default_logprob_threshold() -> f32 {
+    -1.0
+}
+
+fn default_suppress_blank() -> bool {
+    true
+}
+
+fn default_seed_keywords() -> bool {
+    true
+}
+
+fn default_max_distance() -> usize {
+    2
+}
+
+fn default_caption_font() -> String {
+    String::from("Arial")
+}
+
+fn default_caption_font_size() -> u32 {
+    64
+}
+
+fn default_caption_margin() -> u32 {
+    80
+}
+
+fn default_caption_color() -> String {
+    String::from("#FFFFFF")
+}
+
+fn default_caption_outline_color() -> String {
+    String::from("#000000")
+}
+
+fn default_caption_outline() -> f64 {
+    3.0
+}
+
+fn default_caption_highlight_color() -> String {
+    String::from("#FFFF00")
+}
+
+fn default_caption_line_length() -> usize {
+    32
+}
+
+fn default_caption_lines() -> usize {
+    2
+}
+
+fn default_max_line_length() -> usize {
+    42
+}
+
+fn default_max_lines() -> usize {
+    2
+}
+
+fn default_max_cue_duration() -> f64 {
+    5.0
+}
+
+fn default_codec() -> String {
+    String::from("libx264")
+}
+
+fn default_crf() -> u32 {
+    18
+}
+
+fn default_preset() -> String {
+    String::from("veryfast")
+}
+
+fn default_cut_clips() -> bool {
+    true
+}
+
+fn default_title_card_duration() -> f64 {
+    2.0
 }
 
 fn default_audio_tracks() -> Vec<u32> {
@@ -62,6 +754,8 @@ impl Default for Config {
         Self {
             clive: CliveConfig {
                 model: String::from("base"),
+                language: default_language(),
+                task: Task::default(),
             },
             tracks: TracksConfig {
                 audio_tracks: default_audio_tracks(),
@@ -69,7 +763,27 @@ impl Default for Config {
             clips: HashMap::new(),
             output: OutputConfig {
                 directory: default_output_dir(),
+                subtitles: Vec::new(),
+                cut_mode: CutMode::default(),
+                codec: default_codec(),
+                crf: default_crf(),
+                preset: default_preset(),
+                cut_clips: default_cut_clips(),
+                timelines: Vec::new(),
+                markers: Vec::new(),
+                supercut: false,
+                supercut_order: SupercutOrder::default(),
+                title_cards: false,
+                title_card_duration: default_title_card_duration(),
+                crossfade: 0.0,
             },
+            export: ExportConfig::default(),
+            captions: CaptionsConfig::default(),
+            limits: LimitsConfig::default(),
+            merge: MergeConfig::default(),
+            snap: SnapConfig::default(),
+            desilence: DesilenceConfig::default(),
+            whisper: WhisperConfig::default(),
             input_file: None,
         }
     }
@@ -82,7 +796,9 @@ impl Config {
     /// * `path` - Path to the configuration file
     pub fn from_file(path: &Path) -> Result<Self> {
         let contents = fs::read_to_string(path).context("Failed to read config file")?;
-        let config: Config = toml::from_str(&contents).context("Failed to parse config file")?;
+        let mut config: Config =
+            toml::from_str(&contents).context("Failed to parse config file")?;
+        config.compile_patterns()?;
 
         // Ensure output directory exists
         fs::create_dir_all(&config.output.directory)
@@ -116,9 +832,10 @@ impl Config {
         tracks: Option<Vec<u32>>,
         keywords: Vec<String>,
     ) -> Self {
-        let mut config = Config::default();
-
-        config.input_file = Some(input);
+        let mut config = Config {
+            input_file: Some(input),
+            ..Default::default()
+        };
 
         if let Some(output_dir) = output {
             config.output.directory = output_dir;
@@ -137,8 +854,9 @@ impl Config {
             config.clips.insert(
                 keyword,
                 ClipConfig {
-                    start_time: 30,
-                    end_time: 30,
+                    start_time: 30.0,
+                    end_time: 30.0,
+                    ..Default::default()
                 },
             );
         }
@@ -170,8 +888,169 @@ impl Config {
         }
     }
 
+    /// Compile the `pattern` and `glob` of every clip
+    pub fn compile_patterns(&mut self) -> Result<()> {
+        for (name, clip) in &mut self.clips {
+            clip.compile(name)?;
+        }
+        Ok(())
+    }
+
+    /// Hash of the `[whisper]` settings and prompt that change the transcription
+    ///
+    /// Cached transcriptions are keyed on it, so changing the decoding
+    /// parameters or the prompt, including the seeded clip keywords, runs
+    /// Whisper again. The thread count is left out since it only changes the speed.
+    pub fn decoding_key(&self) -> String {
+        let whisper = &self.whisper;
+        let strategy = match whisper.strategy {
+            WhisperStrategy::Greedy => format!("greedy:{}", whisper.best_of),
+            WhisperStrategy::Beam => format!("beam:{}", whisper.beam_size),
+        };
+        stable_hash(&format!(
+            "{} {} {} {} {} {} {} {}",
+            strategy,
+            whisper.temperature,
+            whisper.temperature_inc,
+            whisper.no_speech_threshold,
+            whisper.logprob_threshold,
+            whisper.suppress_blank,
+            whisper.max_len,
+            self.whisper_prompt().unwrap_or_default()
+        ))
+    }
+
+    /// Build the prompt passed to Whisper from `[whisper]` and the clip keywords
+    ///
+    /// The vocabulary and keywords come first as a comma-separated list,
+    /// followed by the initial prompt. Whisper drops the start of prompts
+    /// that are too long, so the initial prompt is kept over the word list.
+    /// Returns `None` when there is nothing to prompt with.
+    pub fn whisper_prompt(&self) -> Option<String> {
+        let mut words: Vec<&str> = self.whisper.vocabulary.iter().map(String::as_str).collect();
+        if self.whisper.seed_keywords {
+            // Sort the clips so the prompt, and with it the transcription, is stable
+            let mut clips: Vec<(&String, &ClipConfig)> = self.clips.iter().collect();
+            clips.sort_by_key(|(name, _)| name.as_str());
+            for (name, clip) in clips {
+                match &clip.query_expression {
+                    Some(query) => words.extend(query.terms()),
+                    // Patterns are not words, the table key only names the clip
+                    None if clip.pattern.is_some() || clip.glob.is_some() => {}
+                    None => words.push(name),
+                }
+                words.extend(clip.aliases.iter().map(String::as_str));
+            }
+        }
+
+        let mut seen: Vec<String> = Vec::new();
+        words.retain(|word| {
+            let word = word.trim().to_lowercase();
+            let new = !word.is_empty() && !seen.contains(&word);
+            seen.push(word);
+            new
+        });
+
+        let mut prompt = words.join(", ");
+        if !prompt.is_empty() {
+            prompt.push('.');
+        }
+        if let Some(initial_prompt) = self.whisper.initial_prompt.as_deref().map(str::trim) {
+            if !prompt.is_empty() && !initial_prompt.is_empty() {
+                prompt.push(' ');
+            }
+            prompt.push_str(initial_prompt);
+        }
+        (!prompt.is_empty()).then_some(prompt)
+    }
+
     /// Validate the configuration
     pub fn validate(&self) -> Result<()> {
+        self.validate_input()?;
+
+        // Validate clip configurations
+        if self.clips.is_empty() {
+            anyhow::bail!("No clips specified");
+        }
+        for (name, clip) in &self.clips {
+            if clip.start_time < 0.0 || clip.end_time < 0.0 {
+                anyhow::bail!(
+                    "clips.{} start_time and end_time must not be negative",
+                    name
+                );
+            }
+            check_durations(
+                &format!("clips.{}", name),
+                clip.min_duration.or(self.limits.min_duration),
+                clip.max_duration.or(self.limits.max_duration),
+            )?;
+        }
+        check_durations("limits", self.limits.min_duration, self.limits.max_duration)?;
+        if self.merge.gap < 0.0 {
+            anyhow::bail!("merge gap must not be negative");
+        }
+        if self.merge.max_hits == Some(0) {
+            anyhow::bail!("merge max_hits must be at least 1");
+        }
+        if self.snap.tolerance < 0.0 {
+            anyhow::bail!("snap tolerance must not be negative");
+        }
+        if self.snap.min_silence <= 0.0 {
+            anyhow::bail!("snap min_silence must be greater than 0");
+        }
+
+        Ok(())
+    }
+
+    /// Validate the transcript export settings
+    pub fn validate_export(&self) -> Result<()> {
+        if self.export.max_line_length == 0 {
+            anyhow::bail!("export.max_line_length must be greater than 0");
+        }
+        if self.export.max_lines == 0 {
+            anyhow::bail!("export.max_lines must be greater than 0");
+        }
+        if self.export.max_cue_duration <= 0.0 {
+            anyhow::bail!("export.max_cue_duration must be greater than 0");
+        }
+        if let Some(format) = self
+            .output
+            .subtitles
+            .iter()
+            .find(|format| !matches!(format, ExportFormat::Srt | ExportFormat::Vtt))
+        {
+            anyhow::bail!(
+                "output.subtitles only supports srt and vtt, got {}",
+                format.extension()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Validate the settings used to encode output videos
+    pub fn validate_output(&self) -> Result<()> {
+        if self.output.crf > 63 {
+            anyhow::bail!("output.crf must be between 0 and 63");
+        }
+        if self.output.codec.is_empty() || self.output.preset.is_empty() {
+            anyhow::bail!("output.codec and output.preset must not be empty");
+        }
+        if self.output.supercut && !self.output.cut_clips {
+            anyhow::bail!("output.supercut needs output.cut_clips");
+        }
+        if self.output.title_card_duration <= 0.0 {
+            anyhow::bail!("output.title_card_duration must be greater than 0");
+        }
+        if self.output.crossfade < 0.0 {
+            anyhow::bail!("output.crossfade must not be negative");
+        }
+
+        Ok(())
+    }
+
+    /// Validate only the settings needed to transcribe the input file
+    pub fn validate_input(&self) -> Result<()> {
         // Check if input file is specified
         if self.input_file.is_none() {
             anyhow::bail!("Input file not specified");
@@ -189,14 +1068,108 @@ impl Config {
             _ => anyhow::bail!("Invalid model name: {}", self.clive.model),
         }
 
+        // Validate language
+        let language = self.clive.language.as_str();
+        if language != "auto" && whisper_rs::get_lang_id(language).is_none() {
+            anyhow::bail!(
+                "Invalid language: {}, use a code like \"es\" or \"auto\"",
+                language
+            );
+        }
+        if self.clive.model.ends_with(".en") && !matches!(language, "en" | "auto") {
+            anyhow::bail!(
+                "Model {} only transcribes English, use a multilingual model for {}",
+                self.clive.model,
+                language
+            );
+        }
+        if self.clive.model.ends_with(".en") && self.clive.task == Task::Translate {
+            anyhow::bail!(
+                "Model {} cannot translate, use a multilingual model",
+                self.clive.model
+            );
+        }
+
         // Validate audio tracks
         if self.tracks.audio_tracks.is_empty() {
             anyhow::bail!("No audio tracks specified");
         }
 
-        // Validate clip configurations
-        if self.clips.is_empty() {
-            anyhow::bail!("No clips specified");
+        self.validate_whisper()
+    }
+
+    /// Validate the Whisper decoding parameters
+    pub fn validate_whisper(&self) -> Result<()> {
+        let whisper = &self.whisper;
+        if whisper.beam_size == 0 || whisper.beam_size > 16 {
+            anyhow::bail!("whisper.beam_size must be between 1 and 16");
+        }
+        if whisper.best_of == 0 || whisper.best_of > 16 {
+            anyhow::bail!("whisper.best_of must be between 1 and 16");
+        }
+        if !(0.0..=1.0).contains(&whisper.temperature) {
+            anyhow::bail!("whisper.temperature must be between 0 and 1");
+        }
+        if !(0.0..=1.0).contains(&whisper.temperature_inc) {
+            anyhow::bail!("whisper.temperature_inc must be between 0 and 1");
+        }
+        if whisper.threads == Some(0) {
+            anyhow::bail!("whisper.threads must be at least 1");
+        }
+        if !(0.0..=1.0).contains(&whisper.no_speech_threshold) {
+            anyhow::bail!("whisper.no_speech_threshold must be between 0 and 1");
+        }
+        if whisper.logprob_threshold > 0.0 {
+            anyhow::bail!("whisper.logprob_threshold must not be positive");
+        }
+        if self
+            .whisper_prompt()
+            .is_some_and(|prompt| prompt.contains('\0'))
+        {
+            anyhow::bail!(
+                "whisper.initial_prompt and whisper.vocabulary must not contain null bytes"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Validate the burned-in caption settings
+    pub fn validate_captions(&self) -> Result<()> {
+        let captions = &self.captions;
+        for (name, color) in [
+            ("color", &captions.color),
+            ("outline_color", &captions.outline_color),
+            ("highlight_color", &captions.highlight_color),
+        ] {
+            let valid = color.len() == 7
+                && color.starts_with('#')
+                && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+            if !valid {
+                anyhow::bail!("captions.{} must be a #RRGGBB color, got {}", name, color);
+            }
+        }
+        if captions.font_size == 0 {
+            anyhow::bail!("captions.font_size must be greater than 0");
+        }
+        if captions.outline < 0.0 {
+            anyhow::bail!("captions.outline must not be negative");
+        }
+        if captions.max_line_length == 0 || captions.max_lines == 0 {
+            anyhow::bail!("captions.max_line_length and captions.max_lines must be greater than 0");
+        }
+
+        Ok(())
+    }
+
+    /// Validate the silence removal settings
+    pub fn validate_desilence(&self) -> Result<()> {
+        let desilence = &self.desilence;
+        if desilence.min_silence <= 0.0 {
+            anyhow::bail!("desilence.min_silence must be greater than 0");
+        }
+        if desilence.padding < 0.0 {
+            anyhow::bail!("desilence.padding must not be negative");
         }
 
         Ok(())
@@ -215,6 +1188,243 @@ mod tests {
         assert_eq!(config.tracks.audio_tracks, vec![1, 2]);
         assert!(config.clips.is_empty());
         assert_eq!(config.output.directory, PathBuf::from("output"));
+        assert!(config.export.formats.is_empty());
+    }
+
+    #[test]
+    fn test_export_config_from_toml() -> Result<()> {
+        let config: Config = toml::from_str(
+            r#"
+            [clive]
+            model = "base"
+
+            [tracks]
+
+            [clips]
+
+            [output]
+
+            [export]
+            formats = ["srt", "json"]
+            max_line_length = 32
+            "#,
+        )?;
+
+        assert_eq!(
+            config.export.formats,
+            vec![ExportFormat::Srt, ExportFormat::Json]
+        );
+        assert_eq!(config.export.max_line_length, 32);
+        assert_eq!(config.export.max_lines, 2);
+        config.validate_export()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_whisper_config_from_toml() -> Result<()> {
+        let mut config: Config = toml::from_str(
+            r#"
+            [clive]
+            model = "base"
+
+            [tracks]
+
+            [clips]
+
+            [output]
+
+            [whisper]
+            strategy = "beam"
+            beam_size = 8
+            threads = 2
+            "#,
+        )?;
+
+        assert_eq!(config.whisper.strategy, WhisperStrategy::Beam);
+        assert!(matches!(
+            config.whisper.sampling_strategy(),
+            SamplingStrategy::BeamSearch { beam_size: 8, .. }
+        ));
+        assert_eq!(config.whisper.threads, Some(2));
+        assert_eq!(config.whisper.temperature_inc, 0.2);
+        assert!(config.whisper.suppress_blank);
+        config.validate_whisper()?;
+
+        // Threads only change the speed, the other parameters the transcription
+        let key = config.decoding_key();
+        config.whisper.threads = Some(4);
+        assert_eq!(config.decoding_key(), key);
+        config.whisper.beam_size = 5;
+        assert_ne!(config.decoding_key(), key);
+
+        config.whisper.temperature = 1.5;
+        assert!(config.validate_whisper().is_err());
+        config.whisper.temperature = 0.0;
+        config.whisper.threads = Some(0);
+        assert!(config.validate_whisper().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_whisper_prompt() -> Result<()> {
+        let mut config: Config = toml::from_str(
+            r#"
+            [clive]
+            model = "base"
+
+            [tracks]
+
+            [clips.valorant]
+            start_time = 10
+            end_time = 10
+            aliases = ["valo", "Valorant"]
+
+            [clips.boss_kill]
+            start_time = 10
+            end_time = 10
+            query = '"boss" AND "dead" WITHIN 10s'
+
+            [clips.insane]
+            start_time = 10
+            end_time = 10
+            pattern = "insan(e|ely)"
+
+            [output]
+
+            [whisper]
+            vocabulary = ["Shroud", "boss"]
+            initial_prompt = "Welcome back to the stream."
+            "#,
+        )?;
+        config.compile_patterns()?;
+
+        assert_eq!(
+            config.whisper_prompt().as_deref(),
+            Some("Shroud, boss, dead, valorant, valo. Welcome back to the stream.")
+        );
+
+        // The prompt is part of the cache key
+        let key = config.decoding_key();
+        config.whisper.seed_keywords = false;
+        assert_ne!(config.decoding_key(), key);
+
+        config.whisper.initial_prompt = None;
+        assert_eq!(config.whisper_prompt().as_deref(), Some("Shroud, boss."));
+
+        config.whisper.vocabulary.clear();
+        assert_eq!(config.whisper_prompt(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cut_mode_from_toml() -> Result<()> {
+        let config: Config = toml::from_str(
+            r#"
+            [clive]
+            model = "base"
+
+            [tracks]
+
+            [clips]
+
+            [output]
+            cut_mode = "smart"
+            crf = 23
+            "#,
+        )?;
+
+        assert_eq!(config.output.cut_mode, CutMode::Smart);
+        assert_eq!(config.output.crf, 23);
+        assert_eq!(config.output.codec, "libx264");
+        config.validate_output()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_supercut_from_toml() -> Result<()> {
+        let mut config: Config = toml::from_str(
+            r#"
+            [clive]
+            model = "base"
+
+            [tracks]
+
+            [clips]
+
+            [output]
+            supercut = true
+            supercut_order = "keyword"
+            crossfade = 0.5
+            "#,
+        )?;
+
+        assert!(config.output.supercut);
+        assert_eq!(config.output.supercut_order, SupercutOrder::Keyword);
+        assert!(!config.output.title_cards);
+        assert_eq!(config.output.title_card_duration, 2.0);
+        config.validate_output()?;
+
+        config.output.crossfade = -1.0;
+        assert!(config.validate_output().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clip_patterns() -> Result<()> {
+        let mut config: Config = toml::from_str(
+            r#"
+            [clive]
+            model = "base"
+
+            [tracks]
+
+            [clips.insane]
+            start_time = 5
+            end_time = 5
+            pattern = "insan(e|ely|ity)"
+
+            [clips.clutch]
+            start_time = 5
+            end_time = 5
+            glob = "clutch*"
+
+            [output]
+            "#,
+        )?;
+        config.compile_patterns()?;
+
+        let insane = config.clips["insane"].matcher.as_ref().unwrap();
+        assert!(insane.is_match("Insanely"));
+        assert!(!insane.is_match("insaner"));
+
+        let clutch = config.clips["clutch"].matcher.as_ref().unwrap();
+        assert!(clutch.is_match("clutched"));
+        assert!(!clutch.is_match("unclutch"));
+
+        config.clips.get_mut("clutch").unwrap().pattern = Some("clutch".to_string());
+        assert!(config.compile_patterns().is_err());
+
+        let mut invalid = ClipConfig {
+            pattern: Some("(".to_string()),
+            ..Default::default()
+        };
+        assert!(invalid.compile("invalid").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_captions() {
+        let mut config = Config::default();
+        assert!(config.validate_captions().is_ok());
+
+        config.captions.highlight_color = "yellow".to_string();
+        assert!(config.validate_captions().is_err());
     }
 
     #[test]
@@ -236,8 +1446,9 @@ mod tests {
         config.clips.insert(
             "test".to_string(),
             ClipConfig {
-                start_time: 10,
-                end_time: 20,
+                start_time: 10.0,
+                end_time: 20.5,
+                ..Default::default()
             },
         );
 
@@ -246,8 +1457,8 @@ mod tests {
 
         let loaded_config = Config::from_file(temp_file.path())?;
         assert_eq!(loaded_config.clips.len(), 1);
-        assert_eq!(loaded_config.clips["test"].start_time, 10);
-        assert_eq!(loaded_config.clips["test"].end_time, 20);
+        assert_eq!(loaded_config.clips["test"].start_time, 10.0);
+        assert_eq!(loaded_config.clips["test"].end_time, 20.5);
 
         Ok(())
     }
@@ -0,0 +1,81 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use super::cache::Clip;
+
+/// What `clive desilence` writes
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DesilenceOutput {
+    /// The input with the silences cut out (re-encodes the video)
+    #[default]
+    Video,
+    /// A CMX3600 edit decision list of the kept ranges
+    Edl,
+}
+
+/// Find the ranges to keep once the silences are cut out
+///
+/// Each silence keeps `padding` seconds next to the speech on either side,
+/// so silences shorter than twice the padding are kept whole.
+///
+/// # Arguments
+/// * `silences` - Start and end of each silence in seconds, ordered by time
+/// * `duration` - Length of the input in seconds
+/// * `padding` - Seconds of silence kept next to the speech
+pub fn kept_ranges(silences: &[(f64, f64)], duration: f64, padding: f64) -> Vec<(f64, f64)> {
+    let mut ranges = Vec::new();
+    let mut start = 0.0;
+    for &(silence_start, silence_end) in silences {
+        // Silences touching the start or end of the input need no padding there
+        let cut_start = if silence_start <= 0.0 {
+            0.0
+        } else {
+            silence_start + padding
+        };
+        let cut_end = if silence_end >= duration {
+            duration
+        } else {
+            silence_end - padding
+        };
+        if cut_end <= cut_start {
+            continue;
+        }
+        if cut_start > start {
+            ranges.push((start, cut_start));
+        }
+        start = cut_end;
+    }
+    if start < duration {
+        ranges.push((start, duration));
+    }
+    ranges
+}
+
+/// Turn kept ranges into clips, so they can be written as a timeline
+pub fn ranges_to_clips(ranges: &[(f64, f64)]) -> Vec<Clip> {
+    ranges
+        .iter()
+        .map(|&(start, end)| Clip {
+            start,
+            end,
+            keyword: "speech".to_string(),
+            hits: Vec::new(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kept_ranges() {
+        let silences = [(0.0, 2.0), (5.0, 5.2), (8.0, 10.0), (15.0, 20.0)];
+        assert_eq!(
+            kept_ranges(&silences, 20.0, 0.25),
+            vec![(1.75, 8.25), (9.75, 15.25)]
+        );
+        assert_eq!(kept_ranges(&[], 20.0, 0.25), vec![(0.0, 20.0)]);
+    }
+}
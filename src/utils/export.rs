@@ -0,0 +1,630 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::cache::Timestamp;
+use super::config::{CaptionHighlight, CaptionPosition, CaptionsConfig, ExportConfig};
+
+/// Transcript formats that can be exported
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// SubRip subtitles
+    Srt,
+    /// WebVTT subtitles
+    Vtt,
+    /// Plain text, one line per segment
+    Txt,
+    /// Segments with nested word-level timestamps
+    Json,
+}
+
+impl ExportFormat {
+    /// File extension for the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Srt => "srt",
+            ExportFormat::Vtt => "vtt",
+            ExportFormat::Txt => "txt",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// A single subtitle cue
+#[derive(Debug, Clone)]
+pub struct Cue {
+    /// Start time in seconds
+    pub start: f64,
+    /// End time in seconds
+    pub end: f64,
+    /// Lines of words shown together
+    pub lines: Vec<Vec<Timestamp>>,
+    /// Lines of the original-language text shown below, for bilingual subtitles
+    pub original: Vec<String>,
+}
+
+impl Cue {
+    /// The text of each line
+    pub fn text_lines(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|word| word.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect()
+    }
+}
+
+/// A transcription segment with its words
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Segment {
+    /// Start time in seconds
+    pub start: f64,
+    /// End time in seconds
+    pub end: f64,
+    /// The full text of the segment
+    pub text: String,
+    /// Word-level timestamps within the segment
+    pub words: Vec<Timestamp>,
+}
+
+/// Format seconds as HH:MM:SS followed by milliseconds
+///
+/// # Arguments
+/// * `seconds` - Time in seconds
+/// * `separator` - Character between seconds and milliseconds (`,` for SRT, `.` for WebVTT)
+pub fn format_timestamp(seconds: f64, separator: char) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        separator,
+        millis % 1000
+    )
+}
+
+/// Sort timestamps by start time, since multiple tracks are transcribed one after another
+fn sorted(timestamps: &[Timestamp]) -> Vec<&Timestamp> {
+    let mut sorted: Vec<&Timestamp> = timestamps.iter().collect();
+    sorted.sort_by(|a, b| a.start.total_cmp(&b.start));
+    sorted
+}
+
+/// Greedily wrap words into lines no longer than `max_length` characters
+fn wrap_lines<'a>(words: &[&'a Timestamp], max_length: usize) -> Vec<Vec<&'a Timestamp>> {
+    let mut lines: Vec<Vec<&Timestamp>> = Vec::new();
+    let mut line_length = 0;
+    for &word in words {
+        let word_length = word.text.chars().count();
+        match lines.last_mut() {
+            Some(line) if line_length + 1 + word_length <= max_length => {
+                line.push(word);
+                line_length += 1 + word_length;
+            }
+            _ => {
+                lines.push(vec![word]);
+                line_length = word_length;
+            }
+        }
+    }
+    lines
+}
+
+/// Group word-level timestamps into subtitle cues
+///
+/// A new cue starts at every segment boundary, after sentence-ending
+/// punctuation, or when the text would no longer fit in the configured
+/// number of lines or the cue would run longer than the maximum duration.
+pub fn build_cues(timestamps: &[Timestamp], config: &ExportConfig) -> Vec<Cue> {
+    let mut cues = Vec::new();
+    let mut words: Vec<&Timestamp> = Vec::new();
+
+    let flush = |words: &mut Vec<&Timestamp>, cues: &mut Vec<Cue>| {
+        if let (Some(first), Some(last)) = (words.first(), words.last()) {
+            cues.push(Cue {
+                start: first.start,
+                end: last.end,
+                lines: wrap_lines(words, config.max_line_length)
+                    .into_iter()
+                    .map(|line| line.into_iter().cloned().collect())
+                    .collect(),
+                original: Vec::new(),
+            });
+        }
+        words.clear();
+    };
+
+    for word in sorted(timestamps) {
+        if let (Some(first), Some(last)) = (words.first(), words.last()) {
+            let new_segment = word.segment != last.segment;
+            let sentence_end = last.text.ends_with(['.', '?', '!']);
+            let too_slow = word.end - first.start > config.max_cue_duration;
+            let mut candidate = words.clone();
+            candidate.push(word);
+            let too_long = wrap_lines(&candidate, config.max_line_length).len() > config.max_lines;
+
+            if new_segment || sentence_end || too_long || too_slow {
+                flush(&mut words, &mut cues);
+            }
+        }
+        words.push(word);
+    }
+    flush(&mut words, &mut cues);
+
+    cues
+}
+
+/// Attach the original-language words to the cues they were spoken in
+///
+/// Each original word goes to the cue containing its midpoint, or the
+/// closest cue when it falls between two, and is wrapped like the cue text.
+///
+/// # Arguments
+/// * `cues` - Cues built from the translated timestamps, ordered by time
+/// * `original` - Word-level timestamps in the spoken language
+/// * `max_line_length` - Maximum characters per line
+pub fn add_originals(cues: &mut [Cue], original: &[Timestamp], max_line_length: usize) {
+    if cues.is_empty() {
+        return;
+    }
+    let mut words: Vec<Vec<&Timestamp>> = vec![Vec::new(); cues.len()];
+    let mut index = 0;
+    for word in sorted(original) {
+        let middle = (word.start + word.end) / 2.0;
+        while index + 1 < cues.len() && cues[index + 1].start <= middle {
+            index += 1;
+        }
+        // Between two cues, pick whichever is closer
+        let closer_next = index + 1 < cues.len()
+            && middle > cues[index].end
+            && cues[index + 1].start - middle < middle - cues[index].end;
+        words[index + usize::from(closer_next)].push(word);
+    }
+
+    for (cue, words) in cues.iter_mut().zip(words) {
+        cue.original = wrap_lines(&words, max_line_length)
+            .into_iter()
+            .map(|line| {
+                line.iter()
+                    .map(|word| word.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+    }
+}
+
+/// Select the timestamps within a clip, shifted so the clip starts at zero
+///
+/// Words that straddle the clip boundaries are kept and clamped to the clip.
+///
+/// # Arguments
+/// * `timestamps` - Word-level timestamps for the whole input
+/// * `start` - Clip start time in seconds
+/// * `end` - Clip end time in seconds
+pub fn clip_timestamps(timestamps: &[Timestamp], start: f64, end: f64) -> Vec<Timestamp> {
+    sorted(timestamps)
+        .into_iter()
+        .filter(|word| word.end > start && word.start < end)
+        .map(|word| Timestamp {
+            start: (word.start - start).max(0.0),
+            end: (word.end.min(end) - start).max(0.0),
+            text: word.text.clone(),
+            segment: word.segment,
+        })
+        .collect()
+}
+
+/// Group word-level timestamps back into their Whisper segments
+pub fn group_segments(timestamps: &[Timestamp]) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+
+    for word in sorted(timestamps) {
+        match segments.last_mut() {
+            Some(segment) if segment.words.last().map(|w| w.segment) == Some(word.segment) => {
+                segment.end = segment.end.max(word.end);
+                segment.text.push(' ');
+                segment.text.push_str(&word.text);
+                segment.words.push(word.clone());
+            }
+            _ => segments.push(Segment {
+                start: word.start,
+                end: word.end,
+                text: word.text.clone(),
+                words: vec![word.clone()],
+            }),
+        }
+    }
+
+    segments
+}
+
+/// Render cues as SubRip subtitles
+pub fn to_srt(cues: &[Cue]) -> String {
+    let mut output = String::new();
+    for (i, cue) in cues.iter().enumerate() {
+        output.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            format_timestamp(cue.start, ','),
+            format_timestamp(cue.end, ','),
+            cue.text_lines()
+                .into_iter()
+                .chain(cue.original.iter().cloned())
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+    output
+}
+
+/// Render cues as WebVTT subtitles
+pub fn to_vtt(cues: &[Cue]) -> String {
+    let mut output = String::from("WEBVTT\n\n");
+    for cue in cues {
+        output.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            format_timestamp(cue.start, '.'),
+            format_timestamp(cue.end, '.'),
+            cue.text_lines()
+                .into_iter()
+                .chain(cue.original.iter().cloned())
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+    output
+}
+
+/// Format seconds as an ASS timestamp (H:MM:SS.cc)
+fn format_ass_timestamp(seconds: f64) -> String {
+    let centis = (seconds.max(0.0) * 100.0).round() as u64;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        centis / 360_000,
+        centis / 6000 % 60,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+/// Convert a #RRGGBB color to the ASS &HAABBGGRR format
+fn ass_color(color: &str) -> String {
+    let hex = color.trim_start_matches('#');
+    match (hex.get(0..2), hex.get(2..4), hex.get(4..6)) {
+        (Some(r), Some(g), Some(b)) => format!("&H00{}{}{}", b, g, r).to_uppercase(),
+        _ => String::from("&H00FFFFFF"),
+    }
+}
+
+/// Render cues as an ASS subtitle script for burning captions into video
+///
+/// # Arguments
+/// * `cues` - Caption cues, timed relative to the start of the video
+/// * `captions` - Caption styling
+/// * `width` - Width of the video in pixels
+/// * `height` - Height of the video in pixels
+pub fn to_ass(cues: &[Cue], captions: &CaptionsConfig, width: u32, height: u32) -> String {
+    // With karaoke tags, text starts in the secondary color and switches to
+    // the primary color as each word is spoken
+    let (primary, secondary) = match captions.highlight {
+        CaptionHighlight::None => (&captions.color, &captions.color),
+        CaptionHighlight::Karaoke => (&captions.highlight_color, &captions.color),
+    };
+    let alignment = match captions.position {
+        CaptionPosition::Bottom => 2,
+        CaptionPosition::Center => 5,
+        CaptionPosition::Top => 8,
+    };
+
+    let mut output = format!(
+        "[Script Info]\n\
+         ScriptType: v4.00+\n\
+         PlayResX: {width}\n\
+         PlayResY: {height}\n\
+         ScaledBorderAndShadow: yes\n\
+         WrapStyle: 2\n\
+         \n\
+         [V4+ Styles]\n\
+         Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, \
+         BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, \
+         BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+         Style: Default,{font},{size},{primary},{secondary},{outline_color},&H80000000,\
+         -1,0,0,0,100,100,0,0,1,{outline},0,{alignment},40,40,{margin},1\n\
+         \n\
+         [Events]\n\
+         Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+        font = captions.font,
+        size = captions.font_size,
+        primary = ass_color(primary),
+        secondary = ass_color(secondary),
+        outline_color = ass_color(&captions.outline_color),
+        outline = captions.outline,
+        margin = captions.margin,
+    );
+
+    for cue in cues {
+        let mut text = String::new();
+        let words: Vec<&Timestamp> = cue.lines.iter().flatten().collect();
+
+        // Lead-in before the first word is spoken
+        if captions.highlight == CaptionHighlight::Karaoke {
+            if let Some(first) = words.first() {
+                let lead = ((first.start - cue.start) * 100.0).round() as u64;
+                if lead > 0 {
+                    text.push_str(&format!("{{\\k{}}}", lead));
+                }
+            }
+        }
+
+        let mut index = 0;
+        for (line_number, line) in cue.lines.iter().enumerate() {
+            if line_number > 0 {
+                text.push_str("\\N");
+            }
+            for (word_number, word) in line.iter().enumerate() {
+                if word_number > 0 {
+                    text.push(' ');
+                }
+                if captions.highlight == CaptionHighlight::Karaoke {
+                    // Each word stays highlighted until the next one starts
+                    let next_start = words.get(index + 1).map_or(word.end, |next| next.start);
+                    let duration = ((next_start - word.start) * 100.0).round().max(1.0) as u64;
+                    text.push_str(&format!("{{\\k{}}}", duration));
+                }
+                text.push_str(&word.text.replace(['{', '}'], ""));
+                index += 1;
+            }
+        }
+
+        output.push_str(&format!(
+            "Dialogue: 0,{},{},Default,,0,0,0,,{}\n",
+            format_ass_timestamp(cue.start),
+            format_ass_timestamp(cue.end),
+            text
+        ));
+    }
+
+    output
+}
+
+/// Render the transcript as plain text, one line per segment
+pub fn to_txt(timestamps: &[Timestamp]) -> String {
+    group_segments(timestamps)
+        .into_iter()
+        .map(|segment| segment.text + "\n")
+        .collect()
+}
+
+/// Render the transcript as JSON segments with nested words
+pub fn to_json(timestamps: &[Timestamp]) -> Result<String> {
+    serde_json::to_string_pretty(&group_segments(timestamps))
+        .context("Failed to serialize transcript")
+}
+
+/// Build the subtitle cues, with the original text below when `bilingual` is set
+fn subtitle_cues(
+    timestamps: &[Timestamp],
+    original: &[Timestamp],
+    config: &ExportConfig,
+) -> Vec<Cue> {
+    let mut cues = build_cues(timestamps, config);
+    if config.bilingual {
+        add_originals(&mut cues, original, config.max_line_length);
+    }
+    cues
+}
+
+/// Render the transcript in the given format
+///
+/// # Arguments
+/// * `timestamps` - Word-level timestamps to export
+/// * `original` - Timestamps in the spoken language for bilingual subtitles, empty if not translated
+/// * `format` - Format to render
+/// * `config` - Subtitle cue settings
+pub fn render(
+    timestamps: &[Timestamp],
+    original: &[Timestamp],
+    format: ExportFormat,
+    config: &ExportConfig,
+) -> Result<String> {
+    Ok(match format {
+        ExportFormat::Srt => to_srt(&subtitle_cues(timestamps, original, config)),
+        ExportFormat::Vtt => to_vtt(&subtitle_cues(timestamps, original, config)),
+        ExportFormat::Txt => to_txt(timestamps),
+        ExportFormat::Json => to_json(timestamps)?,
+    })
+}
+
+/// Write the transcript to `<output_dir>/<name>.<ext>` and return the written path
+///
+/// # Arguments
+/// * `timestamps` - Word-level timestamps to export
+/// * `original` - Timestamps in the spoken language for bilingual subtitles, empty if not translated
+/// * `format` - Format to write
+/// * `config` - Subtitle cue settings
+/// * `output_dir` - Directory where the file will be saved
+/// * `name` - File name without extension
+pub fn write(
+    timestamps: &[Timestamp],
+    original: &[Timestamp],
+    format: ExportFormat,
+    config: &ExportConfig,
+    output_dir: &Path,
+    name: &str,
+) -> Result<PathBuf> {
+    let path = output_dir.join(format!("{}.{}", name, format.extension()));
+    let contents = render(timestamps, original, format, config)?;
+    fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Make text safe to use in a file name
+///
+/// Any character outside `[A-Za-z0-9_-]` is replaced with `_`, so keywords
+/// holding spaces, regex syntax or path separators can't escape the output directory.
+///
+/// # Arguments
+/// * `text` - Text to turn into part of a file name
+pub fn sanitize_file_name(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(start: f64, end: f64, text: &str, segment: usize) -> Timestamp {
+        Timestamp {
+            start,
+            end,
+            text: text.to_string(),
+            segment,
+        }
+    }
+
+    fn sample() -> Vec<Timestamp> {
+        vec![
+            word(0.0, 0.5, "Hello", 0),
+            word(0.5, 1.0, "there", 0),
+            word(1.0, 1.5, "friend.", 0),
+            word(2.0, 2.5, "Next", 1),
+            word(2.5, 3.0, "segment", 1),
+        ]
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(3725.5, ','), "01:02:05,500");
+        assert_eq!(format_timestamp(0.0, '.'), "00:00:00.000");
+    }
+
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(sanitize_file_name("hello world"), "hello_world");
+        assert_eq!(sanitize_file_name("my-clip_2"), "my-clip_2");
+        assert_eq!(sanitize_file_name("../a/b\\c:d*"), "___a_b_c_d_");
+        assert_eq!(sanitize_file_name("colou?r|café"), "colou_r_caf_");
+    }
+
+    #[test]
+    fn test_build_cues_splits_on_segments() {
+        let cues = build_cues(&sample(), &ExportConfig::default());
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].text_lines(), vec!["Hello there friend."]);
+        assert_eq!(cues[0].start, 0.0);
+        assert_eq!(cues[0].end, 1.5);
+        assert_eq!(cues[1].text_lines(), vec!["Next segment"]);
+    }
+
+    #[test]
+    fn test_build_cues_respects_limits() {
+        let config = ExportConfig {
+            max_line_length: 12,
+            max_lines: 1,
+            ..Default::default()
+        };
+        let cues = build_cues(&sample(), &config);
+        assert_eq!(cues[0].text_lines(), vec!["Hello there"]);
+        assert_eq!(cues[1].text_lines(), vec!["friend."]);
+
+        let config = ExportConfig {
+            max_cue_duration: 0.9,
+            ..Default::default()
+        };
+        let cues = build_cues(&sample(), &config);
+        assert_eq!(cues[0].text_lines(), vec!["Hello"]);
+    }
+
+    #[test]
+    fn test_srt_and_vtt() {
+        let cues = build_cues(&sample(), &ExportConfig::default());
+
+        let srt = to_srt(&cues);
+        assert!(srt.starts_with("1\n00:00:00,000 --> 00:00:01,500\nHello there friend.\n\n2\n"));
+
+        let vtt = to_vtt(&cues);
+        assert!(vtt.starts_with("WEBVTT\n\n00:00:00.000 --> 00:00:01.500\n"));
+    }
+
+    #[test]
+    fn test_bilingual_subtitles() {
+        let original = vec![
+            word(0.0, 0.6, "Hola", 0),
+            word(0.6, 1.5, "amigo.", 0),
+            word(1.8, 1.9, "Y", 1),
+            word(2.0, 3.0, "luego", 1),
+        ];
+        let config = ExportConfig {
+            bilingual: true,
+            ..Default::default()
+        };
+
+        let srt = render(&sample(), &original, ExportFormat::Srt, &config).unwrap();
+        assert!(srt.starts_with(
+            "1\n00:00:00,000 --> 00:00:01,500\nHello there friend.\nHola amigo.\n\n2\n"
+        ));
+        assert!(srt.contains("Next segment\nY luego\n"));
+
+        let srt = render(
+            &sample(),
+            &original,
+            ExportFormat::Srt,
+            &ExportConfig::default(),
+        );
+        assert!(!srt.unwrap().contains("Hola"));
+    }
+
+    #[test]
+    fn test_ass_karaoke() {
+        let cues = build_cues(&sample(), &ExportConfig::default());
+        let captions = CaptionsConfig {
+            highlight: CaptionHighlight::Karaoke,
+            ..Default::default()
+        };
+
+        let ass = to_ass(&cues, &captions, 1080, 1920);
+        assert!(ass.contains("PlayResX: 1080\nPlayResY: 1920\n"));
+        assert!(ass.contains("Style: Default,Arial,64,&H0000FFFF,&H00FFFFFF,&H00000000,"));
+        assert!(ass.contains(
+            "Dialogue: 0,0:00:00.00,0:00:01.50,Default,,0,0,0,,{\\k50}Hello {\\k50}there {\\k50}friend.\n"
+        ));
+        assert!(ass.contains("0:00:02.00,0:00:03.00"));
+    }
+
+    #[test]
+    fn test_clip_timestamps() {
+        let words = clip_timestamps(&sample(), 0.75, 2.25);
+        let text: Vec<&str> = words.iter().map(|word| word.text.as_str()).collect();
+        assert_eq!(text, vec!["there", "friend.", "Next"]);
+        assert_eq!(words[0].start, 0.0);
+        assert_eq!(words[1].start, 0.25);
+        assert_eq!(words[2].end, 1.5);
+    }
+
+    #[test]
+    fn test_group_segments() {
+        let segments = group_segments(&sample());
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].text, "Hello there friend.");
+        assert_eq!(segments[0].words.len(), 3);
+        assert_eq!(segments[1].start, 2.0);
+        assert_eq!(to_txt(&sample()), "Hello there friend.\nNext segment\n");
+    }
+}
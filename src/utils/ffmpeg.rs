@@ -1,11 +1,291 @@
 use anyhow::{Context, Result};
+use log::debug;
+use std::fmt;
 use std::path::Path;
-use std::process::Command;
+use std::process::{Command, Output};
+
+use super::config::{CaptionsConfig, CutMode, OutputConfig};
+
+/// Number of stderr lines kept in an [`FFmpegError`]
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Error returned when an ffmpeg or ffprobe command fails
+#[derive(Debug)]
+pub struct FFmpegError {
+    /// What the command was supposed to do
+    pub action: String,
+    /// The exact command line that was run
+    pub command: String,
+    /// Exit code of the process, if it exited normally
+    pub status: Option<i32>,
+    /// The last lines the process wrote to stderr
+    pub stderr: String,
+}
+
+impl fmt::Display for FFmpegError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            Some(code) => write!(f, "Failed to {} (exit code {})", self.action, code)?,
+            None => write!(f, "Failed to {} (terminated by signal)", self.action)?,
+        }
+        write!(f, "\ncommand: {}", self.command)?;
+        if !self.stderr.is_empty() {
+            write!(f, "\n{}", self.stderr)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FFmpegError {}
+
+/// Properties of the first video stream reported by ffprobe
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoInfo {
+    /// Frame rate as a numerator and denominator, e.g. 30000/1001
+    pub frame_rate: (u32, u32),
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Duration of the file in seconds
+    pub duration: f64,
+    /// Start timecode embedded in the file, if any
+    pub timecode: Option<String>,
+}
+
+impl VideoInfo {
+    /// Parses the JSON written by `ffprobe -of json`
+    fn from_ffprobe_json(json: &str) -> Result<Self> {
+        let probe: serde_json::Value = serde_json::from_str(json)?;
+        let stream = &probe["streams"][0];
+        let format = &probe["format"];
+
+        let (num, den) = stream["r_frame_rate"]
+            .as_str()
+            .and_then(|rate| rate.split_once('/'))
+            .context("Failed to parse video frame rate")?;
+        let frame_rate = (num.parse()?, den.parse()?);
+        if frame_rate.0 == 0 || frame_rate.1 == 0 {
+            anyhow::bail!("Video has no usable frame rate");
+        }
+
+        let dimension = |key: &str| -> Result<u32> {
+            let value = stream[key]
+                .as_u64()
+                .with_context(|| format!("Failed to parse video {}", key))?;
+            Ok(u32::try_from(value)?)
+        };
+
+        let duration = format["duration"]
+            .as_str()
+            .context("Failed to parse video duration")?
+            .parse()?;
+
+        // The timecode can be stored on the stream (mov) or the container (mxf)
+        let timecode = stream["tags"]["timecode"]
+            .as_str()
+            .or_else(|| format["tags"]["timecode"].as_str())
+            .map(String::from);
+
+        Ok(Self {
+            frame_rate,
+            width: dimension("width")?,
+            height: dimension("height")?,
+            duration,
+            timecode,
+        })
+    }
+
+    /// Frame rate in frames per second
+    pub fn fps(&self) -> f64 {
+        self.frame_rate.0 as f64 / self.frame_rate.1 as f64
+    }
+}
+
+/// Encoding parameters of the first video stream, matched when re-encoding part of it
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoStream {
+    /// Codec name, e.g. `h264` or `hevc`
+    pub codec: String,
+    /// Codec profile as reported by ffprobe, e.g. `High` or `Main 10`
+    pub profile: Option<String>,
+    /// Codec level as reported by ffprobe, e.g. 41 for H.264 level 4.1
+    pub level: Option<i64>,
+    /// Pixel format, e.g. `yuv420p10le`
+    pub pix_fmt: Option<String>,
+    /// Time base as a numerator and denominator, e.g. 1/90000
+    pub time_base: Option<(u32, u32)>,
+    /// Sample aspect ratio, e.g. `1:1`
+    pub sample_aspect_ratio: Option<String>,
+}
+
+impl VideoStream {
+    /// Parses the JSON written by `ffprobe -of json`
+    fn from_ffprobe_json(json: &str) -> Result<Self> {
+        let probe: serde_json::Value = serde_json::from_str(json)?;
+        let stream = &probe["streams"][0];
+        let text = |key: &str| {
+            stream[key]
+                .as_str()
+                .filter(|value| !value.is_empty() && *value != "unknown")
+                .map(String::from)
+        };
+
+        let time_base = stream["time_base"]
+            .as_str()
+            .and_then(|time_base| time_base.split_once('/'))
+            .and_then(|(num, den)| Some((num.parse().ok()?, den.parse().ok()?)))
+            .filter(|&(num, den): &(u32, u32)| num > 0 && den > 0);
+
+        Ok(Self {
+            codec: text("codec_name").context("Failed to parse video codec")?,
+            profile: text("profile"),
+            level: stream["level"].as_i64().filter(|&level| level > 0),
+            pix_fmt: text("pix_fmt"),
+            time_base,
+            sample_aspect_ratio: text("sample_aspect_ratio").filter(|sar| sar != "0:1"),
+        })
+    }
+
+    /// Encoder arguments that reproduce the stream's codec, profile, level,
+    /// pixel format and aspect ratio
+    ///
+    /// Returns `None` when the stream can't be matched, since an encoded
+    /// part that differs can't be joined with stream-copied parts.
+    pub fn encoder_args(&self) -> Option<Vec<String>> {
+        let profile = self.profile.as_deref()?;
+        let level = self.level?;
+        let pix_fmt = self.pix_fmt.as_deref()?;
+
+        let mut args: Vec<String> = match self.codec.as_str() {
+            "h264" => {
+                let profile = match profile {
+                    "Baseline" | "Constrained Baseline" => "baseline",
+                    "Main" => "main",
+                    "High" => "high",
+                    "High 10" => "high10",
+                    "High 4:2:2" => "high422",
+                    "High 4:4:4 Predictive" => "high444",
+                    _ => return None,
+                };
+                // Levels are reported times ten, e.g. 41 for 4.1
+                let level = format!("{}.{}", level / 10, level % 10);
+                ["-c:v", "libx264", "-profile:v", profile, "-level:v", &level]
+                    .map(String::from)
+                    .to_vec()
+            }
+            "hevc" => {
+                let profile = match profile {
+                    "Main" => "main",
+                    "Main 10" => "main10",
+                    _ => return None,
+                };
+                // Levels are reported times thirty, e.g. 123 for 4.1
+                let level = format!("level-idc={}.{}", level / 30, level / 3 % 10);
+                [
+                    "-c:v",
+                    "libx265",
+                    "-profile:v",
+                    profile,
+                    "-x265-params",
+                    &level,
+                ]
+                .map(String::from)
+                .to_vec()
+            }
+            _ => return None,
+        };
+
+        args.extend(["-pix_fmt", pix_fmt].map(String::from));
+        if let Some(sar) = &self.sample_aspect_ratio {
+            args.extend([
+                String::from("-vf"),
+                format!("setsar={}", sar.replace(':', "/")),
+            ]);
+        }
+        Some(args)
+    }
+}
 
 /// Handles all FFMPEG-related operations for video and audio processing
 pub struct FFmpeg;
 
 impl FFmpeg {
+    /// Runs a command and turns a non-zero exit status into an [`FFmpegError`]
+    ///
+    /// # Arguments
+    /// * `action` - What the command does, used in error messages
+    /// * `command` - The command to run
+    fn run(action: &str, command: &mut Command) -> Result<Output> {
+        let command_line = Self::command_line(command);
+        debug!("Running {}", command_line);
+
+        let output = command
+            .output()
+            .with_context(|| format!("Failed to {}: could not run {}", action, command_line))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let lines: Vec<&str> = stderr.lines().collect();
+            let tail = lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..].join("\n");
+            return Err(FFmpegError {
+                action: action.to_string(),
+                command: command_line,
+                status: output.status.code(),
+                stderr: tail,
+            }
+            .into());
+        }
+
+        Ok(output)
+    }
+
+    /// Formats a command as a copy-pasteable command line
+    fn command_line(command: &Command) -> String {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| {
+                let arg = arg.to_string_lossy();
+                if arg.is_empty()
+                    || arg.contains(|c: char| c.is_whitespace() || c == '\'' || c == '"')
+                {
+                    format!("{:?}", arg)
+                } else {
+                    arg.into_owned()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Checks that an output file exists and is about as long as expected
+    ///
+    /// # Arguments
+    /// * `output_path` - Path to the file that should have been written
+    /// * `expected_duration` - Requested duration in seconds
+    pub fn verify_output(output_path: &Path, expected_duration: f64) -> Result<()> {
+        let size = std::fs::metadata(output_path)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        if size == 0 {
+            anyhow::bail!("FFmpeg did not write {}", output_path.display());
+        }
+
+        let duration = Self::get_duration(output_path)
+            .with_context(|| format!("Failed to read the duration of {}", output_path.display()))?;
+        let tolerance = (expected_duration * 0.05).max(1.0);
+        if (duration - expected_duration).abs() > tolerance {
+            anyhow::bail!(
+                "{} is {:.2}s long, expected {:.2}s",
+                output_path.display(),
+                duration,
+                expected_duration
+            );
+        }
+
+        Ok(())
+    }
+
     /// Checks if FFmpeg is available on the system
     pub fn check_ffmpeg() -> Result<()> {
         Command::new("ffmpeg")
@@ -55,18 +335,7 @@ impl FFmpeg {
             output_path.to_str().unwrap(),
         ]);
 
-        let output = Command::new("ffmpeg")
-            .args(&args)
-            .output()
-            .context("Failed to run ffmpeg command")?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow::anyhow!(
-                "FFmpeg failed to extract audio tracks: {}",
-                stderr
-            ));
-        }
+        Self::run("extract audio tracks", Command::new("ffmpeg").args(&args))?;
 
         Ok(())
     }
@@ -78,14 +347,78 @@ impl FFmpeg {
     /// * `output_path` - Path where the clip will be saved
     /// * `start_time` - Start time in seconds
     /// * `end_time` - End time in seconds
+    /// * `options` - Cut mode and encoder settings
     pub fn create_clip(
         input_path: &Path,
         output_path: &Path,
         start_time: f64,
         end_time: f64,
+        options: &OutputConfig,
     ) -> Result<()> {
-        Command::new("ffmpeg")
-            .args([
+        match options.cut_mode {
+            CutMode::Copy => Self::copy_clip(input_path, output_path, start_time, end_time)?,
+            CutMode::Accurate => Self::encode_clip(
+                input_path,
+                output_path,
+                start_time,
+                end_time,
+                &options.codec,
+                options,
+                None,
+            )?,
+            CutMode::Smart => {
+                Self::smart_clip(input_path, output_path, start_time, end_time, options)?
+            }
+        }
+
+        let expected_duration = Self::get_duration(input_path)?.min(end_time) - start_time;
+        Self::verify_output(output_path, expected_duration)
+    }
+
+    /// Creates a clip with subtitles burned into the video
+    ///
+    /// The input is seeked before decoding, so the subtitle timings must be
+    /// relative to `start_time`. The video stream is always re-encoded.
+    ///
+    /// # Arguments
+    /// * `input_path` - Path to the input video file
+    /// * `output_path` - Path where the clip will be saved
+    /// * `start_time` - Start time in seconds
+    /// * `end_time` - End time in seconds
+    /// * `subtitles_path` - Path to an ASS/SRT subtitle file to render
+    /// * `options` - Encoder settings
+    pub fn create_captioned_clip(
+        input_path: &Path,
+        output_path: &Path,
+        start_time: f64,
+        end_time: f64,
+        subtitles_path: &Path,
+        options: &OutputConfig,
+    ) -> Result<()> {
+        Self::encode_clip(
+            input_path,
+            output_path,
+            start_time,
+            end_time,
+            &options.codec,
+            options,
+            Some(&Self::subtitles_filter(subtitles_path)),
+        )?;
+
+        let expected_duration = Self::get_duration(input_path)?.min(end_time) - start_time;
+        Self::verify_output(output_path, expected_duration)
+    }
+
+    /// Cuts a clip with stream copy, which snaps to the nearest keyframes
+    fn copy_clip(
+        input_path: &Path,
+        output_path: &Path,
+        start_time: f64,
+        end_time: f64,
+    ) -> Result<()> {
+        Self::run(
+            "create video clip",
+            Command::new("ffmpeg").args([
                 "-i",
                 input_path.to_str().unwrap(),
                 "-ss",
@@ -98,13 +431,572 @@ impl FFmpeg {
                 "copy", // Copy audio stream without re-encoding
                 output_path.to_str().unwrap(),
                 "-y",
-            ])
-            .output()
-            .context("Failed to create video clip")?;
+            ]),
+        )?;
+
+        Ok(())
+    }
+
+    /// Cuts a frame-accurate clip by re-encoding the video stream
+    fn encode_clip(
+        input_path: &Path,
+        output_path: &Path,
+        start_time: f64,
+        end_time: f64,
+        codec: &str,
+        options: &OutputConfig,
+        video_filter: Option<&str>,
+    ) -> Result<()> {
+        let start = start_time.to_string();
+        let duration = (end_time - start_time).to_string();
+        let crf = options.crf.to_string();
+
+        // Seeking before the input is frame-accurate when re-encoding
+        let mut args = vec![
+            "-ss",
+            &start,
+            "-i",
+            input_path.to_str().unwrap(),
+            "-t",
+            &duration,
+        ];
+        if let Some(filter) = video_filter {
+            args.extend_from_slice(&["-vf", filter]);
+        }
+        args.extend_from_slice(&[
+            "-c:v",
+            codec,
+            "-crf",
+            &crf,
+            "-preset",
+            &options.preset,
+            "-c:a",
+            "copy",
+            output_path.to_str().unwrap(),
+            "-y",
+        ]);
+
+        Self::run("encode video clip", Command::new("ffmpeg").args(&args))?;
 
         Ok(())
     }
 
+    /// Re-encodes only the frames up to the first keyframe, then stream-copies the rest
+    ///
+    /// The head is encoded with the source's codec, profile, level, pixel
+    /// format, aspect ratio and timescale so the two parts can be joined
+    /// without re-encoding. Audio is stream-copied in both parts. Falls back
+    /// to an accurate cut when the source stream can't be matched.
+    fn smart_clip(
+        input_path: &Path,
+        output_path: &Path,
+        start_time: f64,
+        end_time: f64,
+        options: &OutputConfig,
+    ) -> Result<()> {
+        let keyframe = Self::get_keyframe_after(input_path, start_time)?;
+        let stream = Self::get_video_stream(input_path)?;
+
+        let (keyframe, encoder_args) = match (keyframe, stream.encoder_args()) {
+            // The clip already starts on a keyframe
+            (Some(keyframe), _) if keyframe - start_time < 0.001 => {
+                return Self::copy_clip(input_path, output_path, keyframe, end_time);
+            }
+            (Some(keyframe), Some(args)) if keyframe < end_time => (keyframe, args),
+            _ => {
+                debug!("Falling back to an accurate cut for {:?}", stream);
+                return Self::encode_clip(
+                    input_path,
+                    output_path,
+                    start_time,
+                    end_time,
+                    &options.codec,
+                    options,
+                    None,
+                );
+            }
+        };
+        debug!(
+            "Re-encoding {}s -> {}s, copying {}s -> {}s",
+            start_time, keyframe, keyframe, end_time
+        );
+
+        let extension = output_path
+            .extension()
+            .map(|ext| ext.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("mp4"));
+        let head = tempfile::Builder::new()
+            .suffix(&format!(".{}", extension))
+            .tempfile()?;
+        let tail = tempfile::Builder::new()
+            .suffix(&format!(".{}", extension))
+            .tempfile()?;
+
+        // Both parts get the source timescale so their timestamps line up
+        let mut muxer_args: Vec<String> = Vec::new();
+        if let Some((_, timescale)) = stream.time_base {
+            if matches!(extension.as_str(), "mp4" | "mov" | "m4v") {
+                muxer_args.extend([
+                    String::from("-video_track_timescale"),
+                    timescale.to_string(),
+                ]);
+            }
+        }
+
+        let start = start_time.to_string();
+        let head_duration = (keyframe - start_time).to_string();
+        let crf = options.crf.to_string();
+        let mut head_args: Vec<&str> = vec!["-ss", &start, "-i", input_path.to_str().unwrap()];
+        head_args.extend(["-t", &head_duration]);
+        head_args.extend(encoder_args.iter().map(String::as_str));
+        head_args.extend(["-crf", &crf, "-preset", &options.preset, "-c:a", "copy"]);
+        head_args.extend(muxer_args.iter().map(String::as_str));
+        head_args.extend(["-avoid_negative_ts", "make_zero"]);
+        head_args.extend([head.path().to_str().unwrap(), "-y"]);
+
+        // The encoder may not support the source's profile or pixel format
+        if let Err(e) = Self::run("encode clip head", Command::new("ffmpeg").args(&head_args)) {
+            debug!("Falling back to an accurate cut: {:#}", e);
+            return Self::encode_clip(
+                input_path,
+                output_path,
+                start_time,
+                end_time,
+                &options.codec,
+                options,
+                None,
+            );
+        }
+
+        // Seeking to a keyframe before the input keeps the copied part intact
+        let keyframe_start = keyframe.to_string();
+        let tail_duration = (end_time - keyframe).to_string();
+        let mut tail_args: Vec<&str> =
+            vec!["-ss", &keyframe_start, "-i", input_path.to_str().unwrap()];
+        tail_args.extend(["-t", &tail_duration, "-c", "copy"]);
+        tail_args.extend(muxer_args.iter().map(String::as_str));
+        tail_args.extend(["-avoid_negative_ts", "make_zero"]);
+        tail_args.extend([tail.path().to_str().unwrap(), "-y"]);
+        Self::run("copy video clip", Command::new("ffmpeg").args(&tail_args))?;
+
+        Self::combine_clips(&[head.path(), tail.path()], output_path)
+    }
+
+    /// Finds the first video keyframe at or after the given time
+    ///
+    /// Times are relative to the start of the file, as used by `-ss`, even
+    /// for inputs whose timestamps don't start at zero such as MPEG-TS.
+    ///
+    /// # Arguments
+    /// * `input_path` - Path to the input video file
+    /// * `time` - Time in seconds from the start of the file to search from
+    pub fn get_keyframe_after(input_path: &Path, time: f64) -> Result<Option<f64>> {
+        let start = Self::get_start_time(input_path)?;
+        let output = Self::run(
+            "find keyframes",
+            Command::new("ffprobe").args([
+                "-v",
+                "quiet",
+                "-select_streams",
+                "v:0",
+                "-skip_frame",
+                "nokey",
+                "-read_intervals",
+                &format!("{}%+30", start + time),
+                "-show_entries",
+                "frame=pts_time",
+                "-of",
+                "csv=p=0",
+                input_path.to_str().unwrap(),
+            ]),
+        )?;
+
+        Ok(Self::parse_keyframe_after(
+            &String::from_utf8(output.stdout)?,
+            start,
+            time,
+        ))
+    }
+
+    /// Finds the first keyframe at or after `time` in ffprobe `pts_time` lines
+    ///
+    /// # Arguments
+    /// * `keyframes` - One stream timestamp per line, as written by `-of csv=p=0`
+    /// * `start` - Container start time, subtracted to make the timestamps file-relative
+    /// * `time` - Time in seconds from the start of the file to search from
+    fn parse_keyframe_after(keyframes: &str, start: f64, time: f64) -> Option<f64> {
+        keyframes
+            .lines()
+            .filter_map(|line| line.trim().trim_end_matches(',').parse::<f64>().ok())
+            .map(|pts| pts - start)
+            .find(|&pts| pts >= time)
+    }
+
+    /// Gets the container start time in seconds, 0.0 when it isn't reported
+    ///
+    /// # Arguments
+    /// * `input_path` - Path to the input video file
+    pub fn get_start_time(input_path: &Path) -> Result<f64> {
+        let output = Self::run(
+            "get start time",
+            Command::new("ffprobe").args([
+                "-v",
+                "quiet",
+                "-show_entries",
+                "format=start_time",
+                "-of",
+                "default=noprint_wrappers=1:nokey=1",
+                input_path.to_str().unwrap(),
+            ]),
+        )?;
+
+        Ok(String::from_utf8(output.stdout)?
+            .trim()
+            .parse()
+            .unwrap_or(0.0))
+    }
+
+    /// Gets the encoding parameters of the first video stream
+    ///
+    /// # Arguments
+    /// * `input_path` - Path to the input video file
+    pub fn get_video_stream(input_path: &Path) -> Result<VideoStream> {
+        let output = Self::run(
+            "get video stream",
+            Command::new("ffprobe").args([
+                "-v",
+                "quiet",
+                "-select_streams",
+                "v:0",
+                "-show_entries",
+                "stream=codec_name,profile,level,pix_fmt,time_base,sample_aspect_ratio",
+                "-of",
+                "json",
+                input_path.to_str().unwrap(),
+            ]),
+        )?;
+
+        VideoStream::from_ffprobe_json(&String::from_utf8(output.stdout)?)
+    }
+
+    /// Builds a `subtitles` filter for the given subtitle file
+    ///
+    /// Backslashes are normalized to forward slashes and characters that are
+    /// special inside a filter graph are escaped.
+    pub fn subtitles_filter(subtitles_path: &Path) -> String {
+        format!("subtitles={}", Self::filter_path(subtitles_path))
+    }
+
+    /// Quotes a file path for use as a filter option value
+    fn filter_path(path: &Path) -> String {
+        let path = path
+            .to_string_lossy()
+            .replace('\\', "/")
+            .replace(':', "\\:")
+            .replace('\'', "'\\\\\\''");
+        format!("'{}'", path)
+    }
+
+    /// Gets the width and height of the first video stream in pixels
+    ///
+    /// # Arguments
+    /// * `input_path` - Path to the input video file
+    pub fn get_resolution(input_path: &Path) -> Result<(u32, u32)> {
+        let output = Self::run(
+            "get video resolution",
+            Command::new("ffprobe").args([
+                "-v",
+                "quiet",
+                "-select_streams",
+                "v:0",
+                "-show_entries",
+                "stream=width,height",
+                "-of",
+                "csv=p=0:s=x",
+                input_path.to_str().unwrap(),
+            ]),
+        )?;
+
+        let resolution = String::from_utf8(output.stdout)?;
+        let (width, height) = resolution
+            .trim()
+            .split_once('x')
+            .context("Failed to parse video resolution")?;
+
+        Ok((width.parse()?, height.parse()?))
+    }
+
+    /// Gets the frame rate of the first video stream as a rational, e.g. `30000/1001`
+    ///
+    /// # Arguments
+    /// * `input_path` - Path to the input video file
+    pub fn get_frame_rate(input_path: &Path) -> Result<String> {
+        let output = Self::run(
+            "get video frame rate",
+            Command::new("ffprobe").args([
+                "-v",
+                "quiet",
+                "-select_streams",
+                "v:0",
+                "-show_entries",
+                "stream=r_frame_rate",
+                "-of",
+                "csv=p=0",
+                input_path.to_str().unwrap(),
+            ]),
+        )?;
+
+        let frame_rate = String::from_utf8(output.stdout)?.trim().to_string();
+        if frame_rate.is_empty() {
+            anyhow::bail!("Failed to parse video frame rate");
+        }
+
+        Ok(frame_rate)
+    }
+
+    /// Renders a title card showing centered text over a black background
+    ///
+    /// The card has a silent stereo audio track so it can be joined with clips.
+    ///
+    /// # Arguments
+    /// * `text` - Text shown on the card
+    /// * `output_path` - Path where the card will be saved
+    /// * `resolution` - Width and height of the card in pixels
+    /// * `frame_rate` - Frame rate of the card, as returned by `get_frame_rate`
+    /// * `style` - Font, size and color of the text
+    /// * `options` - Card duration and encoder settings
+    pub fn create_title_card(
+        text: &str,
+        output_path: &Path,
+        resolution: (u32, u32),
+        frame_rate: &str,
+        style: &CaptionsConfig,
+        options: &OutputConfig,
+    ) -> Result<()> {
+        // Passing the text through a file avoids escaping it for the filter graph
+        let text_file = tempfile::NamedTempFile::new()?;
+        std::fs::write(text_file.path(), text)?;
+
+        let duration = options.title_card_duration.to_string();
+        let video = format!(
+            "color=c=black:s={}x{}:r={}",
+            resolution.0, resolution.1, frame_rate
+        );
+        let filter = format!(
+            "drawtext=textfile={}:font='{}':fontsize={}:fontcolor={}:x=(w-text_w)/2:y=(h-text_h)/2",
+            Self::filter_path(text_file.path()),
+            style.font,
+            style.font_size,
+            style.color
+        );
+        let crf = options.crf.to_string();
+
+        Self::run(
+            "create title card",
+            Command::new("ffmpeg").args([
+                "-f",
+                "lavfi",
+                "-i",
+                &video,
+                "-f",
+                "lavfi",
+                "-i",
+                "anullsrc=r=48000:cl=stereo",
+                "-t",
+                &duration,
+                "-vf",
+                &filter,
+                "-c:v",
+                &options.codec,
+                "-crf",
+                &crf,
+                "-preset",
+                &options.preset,
+                "-pix_fmt",
+                "yuv420p",
+                "-c:a",
+                "aac",
+                output_path.to_str().unwrap(),
+                "-y",
+            ]),
+        )?;
+
+        Self::verify_output(output_path, options.title_card_duration)
+    }
+
+    /// Joins clips by re-encoding them, optionally crossfading between them
+    ///
+    /// Unlike `combine_clips` the inputs may use different codecs, which is
+    /// needed when title cards are mixed with stream-copied clips.
+    ///
+    /// # Arguments
+    /// * `clip_paths` - Paths to the clips, in playback order
+    /// * `output_path` - Path where the joined video will be saved
+    /// * `frame_rate` - Frame rate of the output, as returned by `get_frame_rate`
+    /// * `options` - Crossfade length and encoder settings
+    pub fn join_clips(
+        clip_paths: &[&Path],
+        output_path: &Path,
+        frame_rate: &str,
+        options: &OutputConfig,
+    ) -> Result<()> {
+        let mut durations = Vec::with_capacity(clip_paths.len());
+        for path in clip_paths {
+            durations.push(Self::get_duration(path)?);
+        }
+
+        // A crossfade can't be longer than the clips it overlaps
+        let shortest = durations.iter().copied().fold(f64::INFINITY, f64::min);
+        let crossfade = options.crossfade.min(shortest / 2.0);
+        let filter = Self::join_filter(&durations, frame_rate, crossfade);
+
+        let crf = options.crf.to_string();
+        let mut args = Vec::new();
+        for path in clip_paths {
+            args.extend_from_slice(&["-i", path.to_str().unwrap()]);
+        }
+        args.extend_from_slice(&[
+            "-filter_complex",
+            &filter,
+            "-map",
+            "[v]",
+            "-map",
+            "[a]",
+            "-c:v",
+            &options.codec,
+            "-crf",
+            &crf,
+            "-preset",
+            &options.preset,
+            "-c:a",
+            "aac",
+            output_path.to_str().unwrap(),
+            "-y",
+        ]);
+
+        Self::run("join video clips", Command::new("ffmpeg").args(&args))?;
+
+        let overlap = crossfade * clip_paths.len().saturating_sub(1) as f64;
+        Self::verify_output(output_path, durations.iter().sum::<f64>() - overlap)
+    }
+
+    /// Builds the filter graph used by `join_clips`, with `[v]` and `[a]` outputs
+    fn join_filter(durations: &[f64], frame_rate: &str, crossfade: f64) -> String {
+        // Normalize every input so the concat and xfade filters accept them
+        let mut filters: Vec<String> = (0..durations.len())
+            .flat_map(|i| {
+                [
+                    format!("[{i}:v]fps={frame_rate},format=yuv420p,setsar=1,settb=AVTB[v{i}]"),
+                    format!("[{i}:a]aformat=sample_rates=48000:channel_layouts=stereo[a{i}]"),
+                ]
+            })
+            .collect();
+
+        if crossfade <= 0.0 || durations.len() < 2 {
+            let inputs: String = (0..durations.len())
+                .map(|i| format!("[v{i}][a{i}]"))
+                .collect();
+            filters.push(format!(
+                "{inputs}concat=n={}:v=1:a=1[v][a]",
+                durations.len()
+            ));
+            return filters.join(";");
+        }
+
+        let mut offset = 0.0;
+        let (mut video, mut audio) = (String::from("v0"), String::from("a0"));
+        for i in 1..durations.len() {
+            offset += durations[i - 1] - crossfade;
+            let last = i == durations.len() - 1;
+            let (next_video, next_audio) = if last {
+                (String::from("v"), String::from("a"))
+            } else {
+                (format!("vx{i}"), format!("ax{i}"))
+            };
+            filters.push(format!(
+                "[{video}][v{i}]xfade=transition=fade:duration={crossfade}:offset={offset}[{next_video}]"
+            ));
+            filters.push(format!(
+                "[{audio}][a{i}]acrossfade=d={crossfade}[{next_audio}]"
+            ));
+            (video, audio) = (next_video, next_audio);
+        }
+
+        filters.join(";")
+    }
+
+    /// Cut everything but the given ranges out of a video, re-encoding it
+    ///
+    /// # Arguments
+    /// * `input_path` - Path to the input video file
+    /// * `output_path` - Path where the tightened video will be saved
+    /// * `ranges` - Start and end of each range to keep in seconds, ordered by time
+    /// * `audio_tracks` - Audio tracks to keep (1-based indexing)
+    /// * `options` - Codec, CRF and preset used for the re-encode
+    pub fn keep_ranges(
+        input_path: &Path,
+        output_path: &Path,
+        ranges: &[(f64, f64)],
+        audio_tracks: &[u32],
+        options: &OutputConfig,
+    ) -> Result<()> {
+        // The select expression grows with every range, so pass it in a file
+        let script = tempfile::NamedTempFile::new()?;
+        std::fs::write(script.path(), Self::keep_filter(ranges, audio_tracks))?;
+
+        let crf = options.crf.to_string();
+        let mut args = vec![
+            "-i",
+            input_path.to_str().unwrap(),
+            "-filter_complex_script",
+            script.path().to_str().unwrap(),
+            "-map",
+            "[v]",
+        ];
+        let audio_labels: Vec<String> =
+            (0..audio_tracks.len()).map(|i| format!("[a{i}]")).collect();
+        for label in &audio_labels {
+            args.extend_from_slice(&["-map", label]);
+        }
+        args.extend_from_slice(&[
+            "-c:v",
+            &options.codec,
+            "-crf",
+            &crf,
+            "-preset",
+            &options.preset,
+            "-c:a",
+            "aac",
+            output_path.to_str().unwrap(),
+            "-y",
+        ]);
+
+        Self::run("cut out silences", Command::new("ffmpeg").args(&args))?;
+
+        let kept: f64 = ranges.iter().map(|(start, end)| end - start).sum();
+        Self::verify_output(output_path, kept)
+    }
+
+    /// Build the filter graph that keeps `ranges` of the video and each audio track
+    fn keep_filter(ranges: &[(f64, f64)], audio_tracks: &[u32]) -> String {
+        let expression = ranges
+            .iter()
+            .map(|(start, end)| format!("between(t,{start},{end})"))
+            .collect::<Vec<_>>()
+            .join("+");
+
+        let mut filters = vec![format!(
+            "[0:v]select='{expression}',setpts=N/FRAME_RATE/TB[v]"
+        )];
+        for (i, track) in audio_tracks.iter().enumerate() {
+            filters.push(format!(
+                "[0:a:{}]aselect='{expression}',asetpts=N/SR/TB[a{i}]",
+                track - 1
+            ));
+        }
+        filters.join(";")
+    }
+
     /// Combines multiple clips into a single video file
     ///
     /// # Arguments
@@ -115,14 +1007,18 @@ impl FFmpeg {
         let temp_file = tempfile::NamedTempFile::new()?;
         let mut file_content = String::new();
 
+        // Entries are resolved relative to the list file, so use absolute paths
         for path in clip_paths {
-            file_content.push_str(&format!("file '{}'\n", path.to_str().unwrap()));
+            let path = std::path::absolute(path)?;
+            let path = path.to_string_lossy().replace('\'', "'\\''");
+            file_content.push_str(&format!("file '{}'\n", path));
         }
 
         std::fs::write(&temp_file, file_content)?;
 
-        Command::new("ffmpeg")
-            .args([
+        Self::run(
+            "combine video clips",
+            Command::new("ffmpeg").args([
                 "-f",
                 "concat",
                 "-safe",
@@ -133,11 +1029,37 @@ impl FFmpeg {
                 "copy",
                 output_path.to_str().unwrap(),
                 "-y",
-            ])
-            .output()
-            .context("Failed to combine video clips")?;
+            ]),
+        )?;
 
-        Ok(())
+        let mut expected_duration = 0.0;
+        for path in clip_paths {
+            expected_duration += Self::get_duration(path)?;
+        }
+        Self::verify_output(output_path, expected_duration)
+    }
+
+    /// Gets the frame rate, resolution, duration and start timecode of a video file
+    ///
+    /// # Arguments
+    /// * `input_path` - Path to the input video file
+    pub fn get_video_info(input_path: &Path) -> Result<VideoInfo> {
+        let output = Self::run(
+            "get video info",
+            Command::new("ffprobe").args([
+                "-v",
+                "quiet",
+                "-select_streams",
+                "v:0",
+                "-show_entries",
+                "stream=r_frame_rate,width,height:stream_tags=timecode:format=duration:format_tags=timecode",
+                "-of",
+                "json",
+                input_path.to_str().unwrap(),
+            ]),
+        )?;
+
+        VideoInfo::from_ffprobe_json(&String::from_utf8(output.stdout)?)
     }
 
     /// Gets the duration of a video file in seconds
@@ -145,8 +1067,9 @@ impl FFmpeg {
     /// # Arguments
     /// * `input_path` - Path to the input video file
     pub fn get_duration(input_path: &Path) -> Result<f64> {
-        let output = Command::new("ffprobe")
-            .args([
+        let output = Self::run(
+            "get video duration",
+            Command::new("ffprobe").args([
                 "-v",
                 "quiet",
                 "-show_entries",
@@ -154,9 +1077,8 @@ impl FFmpeg {
                 "-of",
                 "default=noprint_wrappers=1:nokey=1",
                 input_path.to_str().unwrap(),
-            ])
-            .output()
-            .context("Failed to get video duration")?;
+            ]),
+        )?;
 
         let duration_str = String::from_utf8(output.stdout)?;
         let duration = duration_str.trim().parse::<f64>()?;
@@ -173,4 +1095,198 @@ mod tests {
     fn test_ffmpeg_available() {
         assert!(FFmpeg::check_ffmpeg().is_ok());
     }
+
+    #[test]
+    fn test_subtitles_filter_escaping() {
+        assert_eq!(
+            FFmpeg::subtitles_filter(Path::new("/tmp/captions.ass")),
+            "subtitles='/tmp/captions.ass'"
+        );
+        assert_eq!(
+            FFmpeg::subtitles_filter(Path::new("C:\\clips\\it's.ass")),
+            "subtitles='C\\:/clips/it'\\\\\\''s.ass'"
+        );
+    }
+
+    #[test]
+    fn test_join_filter() {
+        assert_eq!(
+            FFmpeg::join_filter(&[2.0, 3.0], "30", 0.0),
+            "[0:v]fps=30,format=yuv420p,setsar=1,settb=AVTB[v0];\
+             [0:a]aformat=sample_rates=48000:channel_layouts=stereo[a0];\
+             [1:v]fps=30,format=yuv420p,setsar=1,settb=AVTB[v1];\
+             [1:a]aformat=sample_rates=48000:channel_layouts=stereo[a1];\
+             [v0][a0][v1][a1]concat=n=2:v=1:a=1[v][a]"
+        );
+
+        let filter = FFmpeg::join_filter(&[2.0, 3.0, 4.0], "30", 0.5);
+        assert!(filter.contains("[v0][v1]xfade=transition=fade:duration=0.5:offset=1.5[vx1]"));
+        assert!(filter.contains("[vx1][v2]xfade=transition=fade:duration=0.5:offset=4[v]"));
+        assert!(filter.contains("[a0][a1]acrossfade=d=0.5[ax1]"));
+        assert!(filter.ends_with("[ax1][a2]acrossfade=d=0.5[a]"));
+    }
+
+    #[test]
+    fn test_keep_filter() {
+        assert_eq!(
+            FFmpeg::keep_filter(&[(0.0, 1.5), (3.0, 4.25)], &[1, 3]),
+            "[0:v]select='between(t,0,1.5)+between(t,3,4.25)',setpts=N/FRAME_RATE/TB[v];\
+             [0:a:0]aselect='between(t,0,1.5)+between(t,3,4.25)',asetpts=N/SR/TB[a0];\
+             [0:a:2]aselect='between(t,0,1.5)+between(t,3,4.25)',asetpts=N/SR/TB[a1]"
+        );
+    }
+
+    #[test]
+    fn test_parse_keyframe_after() {
+        let keyframes = "0.000000\n2.002000,\n4.004000\n";
+        assert_eq!(
+            FFmpeg::parse_keyframe_after(keyframes, 0.0, 1.0),
+            Some(2.002)
+        );
+        assert_eq!(FFmpeg::parse_keyframe_after(keyframes, 0.0, 5.0), None);
+
+        // MPEG-TS timestamps often start at 1.4s, keyframes are returned relative to the file
+        let keyframes = "1.400000\n3.400000\n5.400000\n";
+        let keyframe = FFmpeg::parse_keyframe_after(keyframes, 1.4, 1.0).unwrap();
+        assert!((keyframe - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_video_stream_encoder_args() -> Result<()> {
+        let stream = VideoStream::from_ffprobe_json(
+            r#"{
+                "streams": [
+                    {
+                        "codec_name": "hevc",
+                        "profile": "Main 10",
+                        "level": 123,
+                        "pix_fmt": "yuv420p10le",
+                        "time_base": "1/90000",
+                        "sample_aspect_ratio": "1:1"
+                    }
+                ]
+            }"#,
+        )?;
+        assert_eq!(stream.time_base, Some((1, 90000)));
+        assert_eq!(
+            stream.encoder_args().unwrap(),
+            [
+                "-c:v",
+                "libx265",
+                "-profile:v",
+                "main10",
+                "-x265-params",
+                "level-idc=4.1",
+                "-pix_fmt",
+                "yuv420p10le",
+                "-vf",
+                "setsar=1/1"
+            ]
+        );
+
+        let stream = VideoStream::from_ffprobe_json(
+            r#"{
+                "streams": [
+                    {
+                        "codec_name": "h264",
+                        "profile": "High",
+                        "level": 41,
+                        "pix_fmt": "yuvj420p",
+                        "sample_aspect_ratio": "0:1"
+                    }
+                ]
+            }"#,
+        )?;
+        assert_eq!(
+            stream.encoder_args().unwrap(),
+            [
+                "-c:v",
+                "libx264",
+                "-profile:v",
+                "high",
+                "-level:v",
+                "4.1",
+                "-pix_fmt",
+                "yuvj420p"
+            ]
+        );
+
+        // Streams that can't be matched fall back to an accurate cut
+        let unmatched = VideoStream {
+            profile: Some(String::from("High 4:4:4 Intra")),
+            ..stream.clone()
+        };
+        assert!(unmatched.encoder_args().is_none());
+        let unmatched = VideoStream {
+            codec: String::from("vp9"),
+            ..stream
+        };
+        assert!(unmatched.encoder_args().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_video_info_from_ffprobe_json() -> Result<()> {
+        let info = VideoInfo::from_ffprobe_json(
+            r#"{
+                "programs": [],
+                "streams": [
+                    {
+                        "width": 1920,
+                        "height": 1080,
+                        "r_frame_rate": "30000/1001",
+                        "tags": { "timecode": "01:00:00;00" }
+                    }
+                ],
+                "format": { "duration": "12.345000" }
+            }"#,
+        )?;
+
+        assert_eq!(info.frame_rate, (30000, 1001));
+        assert_eq!((info.width, info.height), (1920, 1080));
+        assert_eq!(info.duration, 12.345);
+        assert_eq!(info.timecode.as_deref(), Some("01:00:00;00"));
+
+        let info = VideoInfo::from_ffprobe_json(
+            r#"{
+                "streams": [{ "width": 1280, "height": 720, "r_frame_rate": "25/1" }],
+                "format": { "duration": "3.0", "tags": { "timecode": "10:00:00:00" } }
+            }"#,
+        )?;
+        assert_eq!(info.fps(), 25.0);
+        assert_eq!(info.timecode.as_deref(), Some("10:00:00:00"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_line_quotes_arguments() {
+        let mut command = Command::new("ffmpeg");
+        command.args(["-i", "my video.mp4", "-y", ""]);
+        assert_eq!(
+            FFmpeg::command_line(&command),
+            "ffmpeg -i \"my video.mp4\" -y \"\""
+        );
+    }
+
+    #[test]
+    fn test_run_reports_failure() {
+        let error = FFmpeg::run(
+            "list a missing directory",
+            Command::new("ls").arg("/nonexistent/clive"),
+        )
+        .unwrap_err();
+        let error = error.downcast::<FFmpegError>().unwrap();
+        assert_eq!(error.action, "list a missing directory");
+        assert_eq!(error.command, "ls /nonexistent/clive");
+        assert!(error.status.is_some_and(|code| code != 0));
+        assert!(!error.stderr.is_empty());
+        assert!(error.to_string().contains("command: ls /nonexistent/clive"));
+    }
+
+    #[test]
+    fn test_verify_output_rejects_missing_file() {
+        assert!(FFmpeg::verify_output(Path::new("/nonexistent/clip.mp4"), 5.0).is_err());
+    }
 }
@@ -0,0 +1,263 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::ffmpeg::VideoInfo;
+use super::search::Hit;
+use super::timeline::{self, Timecode};
+
+/// YouTube ignores chapters shorter than this many seconds
+const MIN_CHAPTER_LENGTH: f64 = 10.0;
+
+/// Marker formats that can be exported
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkerFormat {
+    /// DaVinci Resolve timeline marker EDL
+    Resolve,
+    /// Adobe Premiere marker CSV
+    Premiere,
+    /// YouTube chapter list for a video description
+    Chapters,
+}
+
+impl MarkerFormat {
+    /// File extension for the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MarkerFormat::Resolve => "edl",
+            MarkerFormat::Premiere => "csv",
+            MarkerFormat::Chapters => "txt",
+        }
+    }
+}
+
+/// Render hits as a DaVinci Resolve marker EDL
+///
+/// Import it with "Timeline > Import > Timeline Markers from EDL" on a
+/// timeline that starts at 01:00:00:00 with the input at its head.
+pub fn to_resolve(hits: &[Hit], title: &str, timecode: &Timecode) -> String {
+    let mut out = format!("TITLE: {}\nFCM: {}\n", title, timecode.fcm());
+    for (i, hit) in hits.iter().enumerate() {
+        let start = timecode.hour() + timecode.frames(hit.start);
+        let duration = timecode
+            .frames(hit.end)
+            .saturating_sub(timecode.frames(hit.start))
+            .max(1);
+        let (start, end) = (timecode.format(start), timecode.format(start + 1));
+        out.push_str(&format!(
+            "\n{:03}  001      V     C        {} {} {} {}  \n |C:ResolveColorBlue |M:{} |D:{}\n",
+            i + 1,
+            start,
+            end,
+            start,
+            end,
+            hit.keyword.replace('|', "/"),
+            duration
+        ));
+    }
+    out
+}
+
+/// Render hits as an Adobe Premiere marker CSV
+///
+/// Timecodes are relative to the start timecode of the input, so they line
+/// up with the source clip.
+pub fn to_premiere(hits: &[Hit], info: &VideoInfo, timecode: &Timecode) -> String {
+    let source_start = timeline::source_start(info, timecode);
+    let mut out = String::from("Marker Name,Description,In,Out,Duration,Marker Type\n");
+    for hit in hits {
+        let start = timecode.frames(hit.start);
+        let end = timecode.frames(hit.end).max(start + 1);
+        out.push_str(&format!(
+            "{},{},{},{},{},Comment\n",
+            csv_field(&hit.keyword),
+            csv_field(&hit.text),
+            timecode.format(source_start + start),
+            timecode.format(source_start + end),
+            timecode.format_duration(end - start)
+        ));
+    }
+    out
+}
+
+/// Render hits as a YouTube chapter list
+///
+/// YouTube needs the first chapter at 0:00 and chapters of at least ten
+/// seconds, so a "Start" chapter is added when needed and hits closer than
+/// that to the previous chapter are dropped.
+pub fn to_chapters(hits: &[Hit]) -> String {
+    let mut chapters: Vec<(f64, &str)> = Vec::new();
+    for hit in hits {
+        let start = hit.start.floor();
+        match chapters.last() {
+            None if start >= MIN_CHAPTER_LENGTH => {
+                chapters.push((0.0, "Start"));
+                chapters.push((start, &hit.keyword));
+            }
+            None => chapters.push((0.0, &hit.keyword)),
+            Some(&(last, _)) if start - last >= MIN_CHAPTER_LENGTH => {
+                chapters.push((start, &hit.keyword))
+            }
+            Some(_) => {}
+        }
+    }
+
+    let hours = chapters.last().is_some_and(|&(start, _)| start >= 3600.0);
+    chapters
+        .iter()
+        .map(|&(start, title)| format!("{} {}\n", chapter_time(start, hours), title))
+        .collect()
+}
+
+/// Format seconds as `M:SS`, or `H:MM:SS` when the list runs past an hour
+fn chapter_time(seconds: f64, hours: bool) -> String {
+    let total = seconds as u64;
+    if hours {
+        format!("{}:{:02}:{:02}", total / 3600, total / 60 % 60, total % 60)
+    } else {
+        format!("{}:{:02}", total / 60, total % 60)
+    }
+}
+
+/// Quote a CSV field when it contains a separator, quote or newline
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Render hits in the given marker format
+///
+/// # Arguments
+/// * `hits` - Keyword matches, ordered by time
+/// * `format` - Format to render
+/// * `info` - Frame rate and start timecode of the input
+/// * `title` - Name written into formats that have one
+pub fn render(hits: &[Hit], format: MarkerFormat, info: &VideoInfo, title: &str) -> String {
+    let timecode = Timecode::for_video(info);
+    match format {
+        MarkerFormat::Resolve => to_resolve(hits, title, &timecode),
+        MarkerFormat::Premiere => to_premiere(hits, info, &timecode),
+        MarkerFormat::Chapters => to_chapters(hits),
+    }
+}
+
+/// Write the markers to `<output_dir>/<name>.<ext>` and return the written path
+///
+/// # Arguments
+/// * `hits` - Keyword matches, ordered by time
+/// * `format` - Format to write
+/// * `info` - Frame rate and start timecode of the input
+/// * `output_dir` - Directory where the file will be saved
+/// * `name` - File name without extension
+pub fn write(
+    hits: &[Hit],
+    format: MarkerFormat,
+    info: &VideoInfo,
+    output_dir: &Path,
+    name: &str,
+) -> Result<PathBuf> {
+    let path = output_dir.join(format!("{}.{}", name, format.extension()));
+    let contents = render(hits, format, info, name);
+    fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(keyword: &str, start: f64, end: f64, text: &str) -> Hit {
+        Hit {
+            keyword: keyword.to_string(),
+            start,
+            end,
+            text: text.to_string(),
+            score: 1.0,
+        }
+    }
+
+    fn info(frame_rate: (u32, u32), timecode: Option<&str>) -> VideoInfo {
+        VideoInfo {
+            frame_rate,
+            width: 1920,
+            height: 1080,
+            duration: 7200.0,
+            timecode: timecode.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_to_resolve() {
+        let hits = vec![hit("hello", 10.0, 10.5, "hello")];
+        assert_eq!(
+            render(&hits, MarkerFormat::Resolve, &info((25, 1), None), "vod"),
+            "TITLE: vod\n\
+             FCM: NON-DROP FRAME\n\
+             \n\
+             001  001      V     C        01:00:10:00 01:00:10:01 01:00:10:00 01:00:10:01  \n \
+             |C:ResolveColorBlue |M:hello |D:13\n"
+        );
+    }
+
+    #[test]
+    fn test_to_premiere() {
+        let hits = vec![hit("gg", 2.0, 2.4, "gg, \"wp\"")];
+        assert_eq!(
+            render(
+                &hits,
+                MarkerFormat::Premiere,
+                &info((25, 1), Some("10:00:00:00")),
+                "vod"
+            ),
+            "Marker Name,Description,In,Out,Duration,Marker Type\n\
+             gg,\"gg, \"\"wp\"\"\",10:00:02:00,10:00:02:10,00:00:00:10,Comment\n"
+        );
+    }
+
+    #[test]
+    fn test_drop_frame_markers() {
+        let info = info((30000, 1001), Some("00:00:00;00"));
+        let hits = vec![hit("clutch", 70.0, 70.5, "clutch")];
+        assert_eq!(
+            render(&hits, MarkerFormat::Resolve, &info, "vod"),
+            "TITLE: vod\n\
+             FCM: DROP FRAME\n\
+             \n\
+             001  001      V     C        01:01:10;00 01:01:10;01 01:01:10;00 01:01:10;01  \n \
+             |C:ResolveColorBlue |M:clutch |D:15\n"
+        );
+
+        // Durations count every frame instead of skipping drop-frame numbers
+        let hits = vec![hit("gg", 59.0, 121.0, "gg")];
+        assert_eq!(
+            render(&hits, MarkerFormat::Premiere, &info, "vod"),
+            "Marker Name,Description,In,Out,Duration,Marker Type\n\
+             gg,gg,00:00:58;28,00:02:01;00,00:01:01:28,Comment\n"
+        );
+    }
+
+    #[test]
+    fn test_to_chapters() {
+        let hits = vec![
+            hit("hello", 65.4, 66.0, "hello"),
+            hit("again", 70.0, 70.5, "again"),
+            hit("bye", 3725.0, 3726.0, "bye"),
+        ];
+        assert_eq!(
+            to_chapters(&hits),
+            "0:00:00 Start\n0:01:05 hello\n1:02:05 bye\n"
+        );
+
+        let hits = vec![
+            hit("intro", 3.0, 3.5, "intro"),
+            hit("end", 90.0, 91.0, "end"),
+        ];
+        assert_eq!(to_chapters(&hits), "0:00 intro\n1:30 end\n");
+    }
+}
@@ -1,7 +1,25 @@
+mod audio;
 mod cache;
 mod config;
+pub mod desilence;
+pub mod export;
 mod ffmpeg;
+pub mod markers;
+pub mod query;
+pub mod search;
+pub mod snap;
+pub mod timeline;
 
-pub use cache::{Cache, Clip, Timestamp};
-pub use config::Config;
-pub use ffmpeg::FFmpeg;
+pub use audio::Envelope;
+pub use cache::{Cache, Clip, Timestamp, Transcription};
+pub use config::{
+    CaptionHighlight, CaptionPosition, CaptionsConfig, ClipConfig, Config, CutMode,
+    DesilenceConfig, ExportConfig, LimitsConfig, MergeConfig, MergeMode, OutputConfig, Overflow,
+    SnapConfig, SnapMode, SupercutOrder, Task, WhisperConfig, WhisperStrategy,
+};
+pub use desilence::DesilenceOutput;
+pub use export::ExportFormat;
+pub use ffmpeg::{FFmpeg, FFmpegError, VideoInfo, VideoStream};
+pub use markers::MarkerFormat;
+pub use search::Hit;
+pub use timeline::TimelineFormat;
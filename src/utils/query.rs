@@ -0,0 +1,463 @@
+use anyhow::{Context, Result};
+
+/// A run of matched words in the transcription
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    /// Index of the first matched word
+    pub first: usize,
+    /// Index of the last matched word
+    pub last: usize,
+    /// Start of the first word in seconds
+    pub start: f64,
+    /// End of the last word in seconds
+    pub end: f64,
+    /// The transcribed text that matched
+    pub text: String,
+    /// Similarity between the text and the query, 1.0 for exact matches
+    pub score: f64,
+}
+
+/// How far apart two terms may be for `AND` and `NOT`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Window {
+    /// Within this many seconds on either side
+    Seconds(f64),
+    /// Within the clip's `start_time` before and `end_time` after
+    Padding,
+}
+
+/// A parsed boolean or proximity keyword query
+///
+/// ```text
+/// "boss" AND "dead" WITHIN 10s
+/// "clutch" NOT "practice"
+/// "first" NEAR/5 "win"
+/// ("gg" OR "good game") AND NOT "ez"
+/// ```
+///
+/// `AND`, `NOT` and `NEAR/N` bind tighter than `OR`. Terms are quoted
+/// phrases or bare words, and operators must be upper case.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// A word or phrase
+    Term(String),
+    /// Both sides within the window, the span covers both
+    And(Box<Query>, Box<Query>, Window),
+    /// The left side with no right side within the window
+    Not(Box<Query>, Box<Query>, Window),
+    /// Both sides with at most this many words between them
+    Near(Box<Query>, Box<Query>, usize),
+    /// Either side
+    Or(Box<Query>, Box<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Term(String),
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Within,
+    Near(usize),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                let term: String = chars.by_ref().take_while(|&c| c != '"').collect();
+                tokens.push(Token::Term(term));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    "WITHIN" => Token::Within,
+                    _ => match word.strip_prefix("NEAR/") {
+                        Some(words) => Token::Near(
+                            words
+                                .parse()
+                                .with_context(|| format!("Invalid word count in {}", word))?,
+                        ),
+                        None => Token::Term(word),
+                    },
+                });
+            }
+        }
+    }
+
+    if !input.matches('"').count().is_multiple_of(2) {
+        anyhow::bail!("Unterminated quote");
+    }
+    Ok(tokens)
+}
+
+/// Parse a duration such as `10s`, `1.5m` or a bare number of seconds
+fn parse_duration(text: &str) -> Result<f64> {
+    let (number, scale) = if let Some(minutes) = text.strip_suffix('m') {
+        (minutes, 60.0)
+    } else {
+        (text.strip_suffix('s').unwrap_or(text), 1.0)
+    };
+    let seconds: f64 = number
+        .parse()
+        .with_context(|| format!("Invalid duration {}", text))?;
+    if seconds < 0.0 {
+        anyhow::bail!("Duration {} must not be negative", text);
+    }
+    Ok(seconds * scale)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Query> {
+        let mut query = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<Query> {
+        let mut query = self.operand()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::And) | Some(Token::Not) | Some(Token::Near(_)) => self.next().unwrap(),
+                _ => return Ok(query),
+            };
+            // `a AND NOT b` reads better than `a NOT b` and means the same
+            if operator == Token::And && self.peek() == Some(&Token::Not) {
+                self.next();
+                let right = self.operand()?;
+                query = Query::Not(Box::new(query), Box::new(right), self.window()?);
+                continue;
+            }
+
+            let right = Box::new(self.operand()?);
+            let left = Box::new(query);
+            query = match operator {
+                Token::And => Query::And(left, right, self.window()?),
+                Token::Not => Query::Not(left, right, self.window()?),
+                Token::Near(words) => Query::Near(left, right, words),
+                _ => unreachable!(),
+            };
+        }
+    }
+
+    fn window(&mut self) -> Result<Window> {
+        if self.peek() != Some(&Token::Within) {
+            return Ok(Window::Padding);
+        }
+        self.next();
+        match self.next() {
+            Some(Token::Term(duration)) => Ok(Window::Seconds(parse_duration(&duration)?)),
+            _ => anyhow::bail!("Expected a duration after WITHIN"),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Query> {
+        match self.next() {
+            Some(Token::Term(term)) if !term.trim().is_empty() => Ok(Query::Term(term)),
+            Some(Token::Open) => {
+                let query = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(query),
+                    _ => anyhow::bail!("Expected a closing parenthesis"),
+                }
+            }
+            Some(token) => anyhow::bail!("Expected a word or phrase, found {:?}", token),
+            None => anyhow::bail!("Expected a word or phrase at the end of the query"),
+        }
+    }
+}
+
+impl Query {
+    /// Parse a query expression
+    ///
+    /// # Arguments
+    /// * `input` - The query, e.g. `"boss" AND "dead" WITHIN 10s`
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            position: 0,
+        };
+        let query = parser.or()?;
+        if let Some(token) = parser.peek() {
+            anyhow::bail!("Unexpected {:?}", token);
+        }
+        Ok(query)
+    }
+
+    /// Every word or phrase in the query, in the order they are written
+    pub fn terms(&self) -> Vec<&str> {
+        match self {
+            Query::Term(term) => vec![term.as_str()],
+            Query::And(left, right, _)
+            | Query::Not(left, right, _)
+            | Query::Near(left, right, _)
+            | Query::Or(left, right) => {
+                let mut terms = left.terms();
+                terms.extend(right.terms());
+                terms
+            }
+        }
+    }
+
+    /// Find every span of words that satisfies the query, ordered by time
+    ///
+    /// # Arguments
+    /// * `terms` - Finds the spans matching a single word or phrase
+    /// * `padding` - Seconds before and after a span used when no `WITHIN` is given
+    pub fn evaluate(
+        &self,
+        terms: &mut dyn FnMut(&str) -> Vec<Span>,
+        padding: (f64, f64),
+    ) -> Vec<Span> {
+        let mut spans = match self {
+            Query::Term(term) => terms(term),
+            Query::Or(left, right) => {
+                let mut spans = left.evaluate(terms, padding);
+                spans.extend(right.evaluate(terms, padding));
+                spans
+            }
+            Query::And(left, right, window) => {
+                let (before, after) = window.bounds(padding);
+                let right = right.evaluate(terms, padding);
+                left.evaluate(terms, padding)
+                    .iter()
+                    .filter_map(|span| {
+                        let nearest = right
+                            .iter()
+                            .filter(|other| {
+                                other.start <= span.end + after && other.end >= span.start - before
+                            })
+                            .min_by(|a, b| span.distance(a).total_cmp(&span.distance(b)))?;
+                        Some(span.join(nearest))
+                    })
+                    .collect()
+            }
+            Query::Not(left, right, window) => {
+                let (before, after) = window.bounds(padding);
+                let right = right.evaluate(terms, padding);
+                left.evaluate(terms, padding)
+                    .into_iter()
+                    .filter(|span| {
+                        !right.iter().any(|other| {
+                            other.start <= span.end + after && other.end >= span.start - before
+                        })
+                    })
+                    .collect()
+            }
+            Query::Near(left, right, words) => {
+                let right = right.evaluate(terms, padding);
+                left.evaluate(terms, padding)
+                    .iter()
+                    .filter_map(|span| {
+                        let nearest = right
+                            .iter()
+                            .filter(|other| span.words_between(other) <= *words)
+                            .min_by_key(|other| span.words_between(other))?;
+                        Some(span.join(nearest))
+                    })
+                    .collect()
+            }
+        };
+
+        spans.sort_by(|a, b| a.start.total_cmp(&b.start).then(a.end.total_cmp(&b.end)));
+        spans.dedup_by(|a, b| a.first == b.first && a.last == b.last);
+        spans
+    }
+}
+
+impl Window {
+    /// Seconds allowed before and after a span
+    fn bounds(&self, padding: (f64, f64)) -> (f64, f64) {
+        match self {
+            Window::Seconds(seconds) => (*seconds, *seconds),
+            Window::Padding => padding,
+        }
+    }
+}
+
+impl Span {
+    /// Seconds between two spans, 0 when they overlap
+    fn distance(&self, other: &Span) -> f64 {
+        (other.start - self.end)
+            .max(self.start - other.end)
+            .max(0.0)
+    }
+
+    /// Number of words between two spans, 0 when they touch or overlap
+    fn words_between(&self, other: &Span) -> usize {
+        if other.first > self.last {
+            other.first - self.last - 1
+        } else if self.first > other.last {
+            self.first - other.last - 1
+        } else {
+            0
+        }
+    }
+
+    /// A span covering both spans
+    fn join(&self, other: &Span) -> Span {
+        let (earlier, later) = if other.start < self.start {
+            (other, self)
+        } else {
+            (self, other)
+        };
+        Span {
+            first: self.first.min(other.first),
+            last: self.last.max(other.last),
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+            text: format!("{} ... {}", earlier.text, later.text),
+            score: self.score.min(other.score),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One span per occurrence of a word, one word per second
+    fn terms<'a>(words: &'a [&'a str]) -> impl FnMut(&str) -> Vec<Span> + 'a {
+        move |term| {
+            words
+                .iter()
+                .enumerate()
+                .filter(|(_, word)| **word == term)
+                .map(|(i, word)| Span {
+                    first: i,
+                    last: i,
+                    start: i as f64,
+                    end: i as f64 + 0.5,
+                    text: word.to_string(),
+                    score: 1.0,
+                })
+                .collect()
+        }
+    }
+
+    fn starts(spans: &[Span]) -> Vec<f64> {
+        spans.iter().map(|span| span.start).collect()
+    }
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let term = |text: &str| Box::new(Query::Term(text.to_string()));
+        assert_eq!(
+            Query::parse(r#""boss" AND "dead" WITHIN 10s"#)?,
+            Query::And(term("boss"), term("dead"), Window::Seconds(10.0))
+        );
+        assert_eq!(
+            Query::parse(r#""clutch" NOT "practice""#)?,
+            Query::Not(term("clutch"), term("practice"), Window::Padding)
+        );
+        assert_eq!(
+            Query::parse(r#"first NEAR/5 "win""#)?,
+            Query::Near(term("first"), term("win"), 5)
+        );
+        assert_eq!(
+            Query::parse(r#"(gg OR "good game") AND NOT ez WITHIN 1m"#)?,
+            Query::Not(
+                Box::new(Query::Or(term("gg"), term("good game"))),
+                term("ez"),
+                Window::Seconds(60.0)
+            )
+        );
+
+        assert!(Query::parse(r#""boss" AND"#).is_err());
+        assert!(Query::parse(r#""boss" OR "dead" WITHIN 10s"#).is_err());
+        assert!(Query::parse(r#"("boss""#).is_err());
+        assert!(Query::parse(r#""boss"#).is_err());
+        assert!(Query::parse("boss NEAR/x dead").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_evaluate() -> Result<()> {
+        let words = [
+            "boss", "x", "x", "dead", "x", "x", "x", "x", "x", "x", "x", "x", "x", "x", "boss",
+        ];
+
+        let query = Query::parse("boss AND dead WITHIN 5s")?;
+        let spans = query.evaluate(&mut terms(&words), (0.0, 0.0));
+        assert_eq!(starts(&spans), vec![0.0]);
+        assert_eq!(
+            (spans[0].end, spans[0].text.as_str()),
+            (3.5, "boss ... dead")
+        );
+
+        let query = Query::parse("boss NOT dead WITHIN 5s")?;
+        assert_eq!(
+            starts(&query.evaluate(&mut terms(&words), (0.0, 0.0))),
+            vec![14.0]
+        );
+
+        // Without WITHIN the clip padding is used
+        let query = Query::parse("boss NOT dead")?;
+        assert_eq!(
+            starts(&query.evaluate(&mut terms(&words), (20.0, 20.0))),
+            Vec::<f64>::new()
+        );
+
+        let query = Query::parse("boss NEAR/2 dead")?;
+        assert_eq!(
+            starts(&query.evaluate(&mut terms(&words), (0.0, 0.0))),
+            vec![0.0]
+        );
+        let query = Query::parse("boss NEAR/1 dead")?;
+        assert!(query.evaluate(&mut terms(&words), (0.0, 0.0)).is_empty());
+
+        let query = Query::parse("dead OR boss")?;
+        assert_eq!(
+            starts(&query.evaluate(&mut terms(&words), (0.0, 0.0))),
+            vec![0.0, 3.0, 14.0]
+        );
+
+        Ok(())
+    }
+}
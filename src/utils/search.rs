@@ -0,0 +1,763 @@
+use serde::{Deserialize, Serialize};
+
+use super::cache::{Clip, Timestamp};
+use super::config::{ClipConfig, Config, MatchMode, MergeMode, Overflow};
+use super::query::Span;
+
+/// A single keyword match in the transcription
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Hit {
+    /// The keyword that matched
+    pub keyword: String,
+    /// Start of the first matched word in seconds
+    pub start: f64,
+    /// End of the last matched word in seconds
+    pub end: f64,
+    /// The transcribed text that matched
+    pub text: String,
+    /// Similarity between the text and the keyword, 1.0 for exact matches
+    pub score: f64,
+}
+
+/// Normalize text into lowercase words without surrounding punctuation
+fn normalize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| c.is_ascii_punctuation())
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+}
+
+/// Find every occurrence of the configured keywords, ordered by time
+///
+/// Keywords with several words match consecutive words in the transcription,
+/// and the hit spans from the first word to the last. Phrases only match
+/// within one Whisper segment unless `cross_segments` is set for the keyword.
+/// The keyword and each of its aliases are compared word by word using the
+/// keyword's `match_mode`. Clips with a compiled `pattern`, `glob` or `query`
+/// match that instead, and the table key is only used as the name of the hit.
+///
+/// # Arguments
+/// * `timestamps` - Word-level timestamps to search
+/// * `config` - Configuration holding the keywords in `[clips]`
+pub fn find_hits(timestamps: &[Timestamp], config: &Config) -> Vec<Hit> {
+    // Flatten the transcription into words, remembering which timestamp each came from
+    let words: Vec<(String, usize)> = timestamps
+        .iter()
+        .enumerate()
+        .flat_map(|(i, timestamp)| normalize(&timestamp.text).map(move |word| (word, i)))
+        .collect();
+
+    let mut hits = Vec::new();
+    for (keyword, clip_config) in &config.clips {
+        let spans = if let Some(matcher) = &clip_config.matcher {
+            words
+                .iter()
+                .enumerate()
+                .filter(|(_, (word, _))| matcher.is_match(word))
+                .map(|(i, _)| span(&words, timestamps, i, i, 1.0))
+                .collect()
+        } else if let Some(query) = &clip_config.query_expression {
+            let padding = (clip_config.start_time, clip_config.end_time);
+            query.evaluate(
+                &mut |term| match_phrases(&words, timestamps, &[term], clip_config),
+                padding,
+            )
+        } else {
+            let phrases: Vec<&str> = std::iter::once(keyword)
+                .chain(&clip_config.aliases)
+                .map(String::as_str)
+                .collect();
+            match_phrases(&words, timestamps, &phrases, clip_config)
+        };
+
+        hits.extend(spans.into_iter().map(|span| Hit {
+            keyword: keyword.clone(),
+            start: span.start,
+            end: span.end,
+            text: span.text,
+            score: span.score,
+        }));
+    }
+
+    // Keywords come from a map, so sort on the keyword too for a stable order
+    hits.sort_by(|a, b| {
+        a.start
+            .total_cmp(&b.start)
+            .then_with(|| a.keyword.cmp(&b.keyword))
+    });
+    hits
+}
+
+/// Find the best match of any of the phrases starting at each word
+///
+/// # Arguments
+/// * `words` - Normalized words and the index of the timestamp each came from
+/// * `timestamps` - Word-level timestamps the words came from
+/// * `phrases` - The keyword and its aliases
+/// * `clip_config` - Match mode and segment settings of the keyword
+fn match_phrases(
+    words: &[(String, usize)],
+    timestamps: &[Timestamp],
+    phrases: &[&str],
+    clip_config: &ClipConfig,
+) -> Vec<Span> {
+    // Keeping one match per start word stops an alias from duplicating the keyword
+    let mut best: Vec<Option<(usize, f64)>> = vec![None; words.len()];
+    for phrase in phrases {
+        let phrase: Vec<String> = normalize(phrase).collect();
+        if phrase.is_empty() {
+            continue;
+        }
+
+        for (start, window) in words.windows(phrase.len()).enumerate() {
+            let scores: Option<Vec<f64>> = window
+                .iter()
+                .zip(&phrase)
+                .map(|((word, _), target)| word_score(word, target, clip_config))
+                .collect();
+            let Some(scores) = scores else {
+                continue;
+            };
+
+            let (first, last) = (window[0].1, window[window.len() - 1].1);
+            if !clip_config.cross_segments && timestamps[first].segment != timestamps[last].segment
+            {
+                continue;
+            }
+
+            let score = scores.iter().sum::<f64>() / scores.len() as f64;
+            if best[start].is_none_or(|(_, best_score)| score > best_score) {
+                best[start] = Some((start + phrase.len() - 1, score));
+            }
+        }
+    }
+
+    best.into_iter()
+        .enumerate()
+        .filter_map(|(first, matched)| {
+            let (last, score) = matched?;
+            Some(span(words, timestamps, first, last, score))
+        })
+        .collect()
+}
+
+/// Build a span from the first to the last matched word
+fn span(
+    words: &[(String, usize)],
+    timestamps: &[Timestamp],
+    first: usize,
+    last: usize,
+    score: f64,
+) -> Span {
+    let (first_timestamp, last_timestamp) = (words[first].1, words[last].1);
+    let text = timestamps[first_timestamp..=last_timestamp]
+        .iter()
+        .map(|timestamp| timestamp.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+    Span {
+        first,
+        last,
+        start: timestamps[first_timestamp].start,
+        end: timestamps[last_timestamp].end,
+        text,
+        score,
+    }
+}
+
+/// Similarity between a spoken word and a keyword word, or `None` if they don't match
+///
+/// Exact matches score 1.0 in every mode. Other matches score by how few
+/// edits separate the two spellings.
+fn word_score(word: &str, target: &str, clip_config: &ClipConfig) -> Option<f64> {
+    if word == target {
+        return Some(1.0);
+    }
+
+    let distance = match clip_config.match_mode {
+        MatchMode::Exact => return None,
+        MatchMode::Fuzzy => {
+            let distance = levenshtein(word, target);
+            // Short keywords would match almost anything with the full distance
+            if distance > clip_config.max_distance || distance * 2 >= target.chars().count() {
+                return None;
+            }
+            distance
+        }
+        MatchMode::Phonetic => {
+            let code = soundex(target);
+            if code.is_empty() || soundex(word) != code {
+                return None;
+            }
+            levenshtein(word, target)
+        }
+    };
+
+    let longest = word.chars().count().max(target.chars().count());
+    Some(1.0 - distance as f64 / longest as f64)
+}
+
+/// Number of single-character insertions, deletions and substitutions between two words
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+
+    row[b.len()]
+}
+
+/// American Soundex code of a word, e.g. `R163` for "Robert"
+///
+/// Returns an empty string when the word has no ASCII letters.
+fn soundex(word: &str) -> String {
+    fn digit(c: char) -> Option<char> {
+        match c {
+            'b' | 'f' | 'p' | 'v' => Some('1'),
+            'c' | 'g' | 'j' | 'k' | 'q' | 's' | 'x' | 'z' => Some('2'),
+            'd' | 't' => Some('3'),
+            'l' => Some('4'),
+            'm' | 'n' => Some('5'),
+            'r' => Some('6'),
+            _ => None,
+        }
+    }
+
+    let mut letters = word
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_lowercase());
+    let Some(first) = letters.next() else {
+        return String::new();
+    };
+
+    let mut code = first.to_ascii_uppercase().to_string();
+    let mut last = digit(first);
+    for c in letters {
+        // h and w don't separate letters with the same code, vowels do
+        if c == 'h' || c == 'w' {
+            continue;
+        }
+        let current = digit(c);
+        if let Some(d) = current {
+            if current != last {
+                code.push(d);
+                if code.len() == 4 {
+                    break;
+                }
+            }
+        }
+        last = current;
+    }
+
+    format!("{:0<4}", code)
+}
+
+/// A padded hit waiting to be merged into a clip
+struct Piece<'a> {
+    hit: &'a Hit,
+    start: f64,
+    end: f64,
+    max_duration: Option<f64>,
+    priority: i32,
+}
+
+/// Pad each hit by its keyword's clip boundaries and merge nearby clips
+///
+/// Clips shorter than `min_duration` grow evenly around their hit. Clips that
+/// overlap or are less than `[merge] gap` apart are merged, across keywords or
+/// per keyword depending on `[merge] mode`. Merged clips longer than the
+/// smallest `max_duration` of their hits, or holding more than `max_hits`
+/// hits, are split between hits, or with `overflow = "drop"` lose their
+/// lowest-priority hits until they fit.
+///
+/// # Arguments
+/// * `hits` - Keyword matches, as returned by `find_hits`
+/// * `config` - Configuration holding the clip boundaries in `[clips]`, `[limits]` and `[merge]`
+pub fn build_clips(hits: &[Hit], config: &Config) -> Vec<Clip> {
+    let mut pieces: Vec<Piece> = hits
+        .iter()
+        .filter_map(|hit| {
+            let clip_config = config.clips.get(&hit.keyword)?;
+            let mut start = hit.start - clip_config.start_time;
+            let mut end = hit.end + clip_config.end_time;
+
+            if let Some(min) = clip_config.min_duration.or(config.limits.min_duration) {
+                let grow = (min - (end - start)).max(0.0) / 2.0;
+                start = (start - grow).max(0.0);
+                end = (end + grow).max(start + min);
+            }
+            start = start.max(0.0);
+
+            let max_duration = clip_config.max_duration.or(config.limits.max_duration);
+            if let Some(max) = max_duration.filter(|&max| end - start > max) {
+                // Keep the window of `max` seconds centered on the hit
+                let center = (hit.start + hit.end) / 2.0;
+                start = (center - max / 2.0).clamp(start, end - max);
+                end = start + max;
+            }
+
+            Some(Piece {
+                hit,
+                start,
+                end,
+                max_duration,
+                priority: clip_config.priority,
+            })
+        })
+        .collect();
+    pieces.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    let merge = &config.merge;
+    let groups = match merge.mode {
+        MergeMode::Never => pieces.into_iter().map(|piece| vec![piece]).collect(),
+        MergeMode::Global => group_pieces(pieces, merge.gap),
+        MergeMode::Keyword => {
+            let mut keywords: Vec<&str> = Vec::new();
+            for piece in &pieces {
+                if !keywords.contains(&piece.hit.keyword.as_str()) {
+                    keywords.push(&piece.hit.keyword);
+                }
+            }
+            let mut by_keyword: Vec<Vec<Piece>> = keywords.iter().map(|_| Vec::new()).collect();
+            for piece in pieces {
+                let i = keywords.iter().position(|k| *k == piece.hit.keyword);
+                by_keyword[i.unwrap()].push(piece);
+            }
+            by_keyword
+                .into_iter()
+                .flat_map(|pieces| group_pieces(pieces, merge.gap))
+                .collect()
+        }
+    };
+
+    // Fit each group into the limits
+    let mut clips: Vec<Clip> = groups
+        .into_iter()
+        .flat_map(|pieces| fit_group(pieces, config))
+        .collect();
+    clips.sort_by(|a, b| a.start.total_cmp(&b.start));
+    clips
+}
+
+/// Group pieces, ordered by start, that overlap or are at most `gap` seconds apart
+fn group_pieces(pieces: Vec<Piece>, gap: f64) -> Vec<Vec<Piece>> {
+    let mut groups: Vec<Vec<Piece>> = Vec::new();
+    for piece in pieces {
+        if let Some(group) = groups.last_mut() {
+            let end = group.iter().map(|p| p.end).fold(f64::MIN, f64::max);
+            if piece.start <= end + gap {
+                group.push(piece);
+                continue;
+            }
+        }
+        groups.push(vec![piece]);
+    }
+    groups
+}
+
+/// Smallest `max_duration` among the pieces, if any of them has one
+fn max_duration(pieces: &[Piece]) -> Option<f64> {
+    pieces
+        .iter()
+        .filter_map(|p| p.max_duration)
+        .min_by(f64::total_cmp)
+}
+
+/// Seconds from the first piece's start to the last piece's end
+fn span_duration(pieces: &[Piece]) -> f64 {
+    let start = pieces.iter().map(|p| p.start).fold(f64::MAX, f64::min);
+    let end = pieces.iter().map(|p| p.end).fold(f64::MIN, f64::max);
+    end - start
+}
+
+/// Whether the pieces fit into one clip within the duration and hit limits
+fn fits(pieces: &[Piece], max_hits: Option<usize>) -> bool {
+    max_hits.is_none_or(|max| pieces.len() <= max)
+        && max_duration(pieces).is_none_or(|max| span_duration(pieces) <= max)
+}
+
+/// Turn a group of pieces, ordered by start, into clips within the limits
+fn fit_group(group: Vec<Piece>, config: &Config) -> Vec<Clip> {
+    let max_hits = config.merge.max_hits;
+    if fits(&group, max_hits) {
+        return vec![merge(&group)];
+    }
+
+    match config.limits.overflow {
+        Overflow::Split => {
+            let mut parts: Vec<Vec<Piece>> = Vec::new();
+            for piece in group {
+                if let Some(part) = parts.last_mut() {
+                    part.push(piece);
+                    if fits(part, max_hits) {
+                        continue;
+                    }
+                    let piece = part.pop().unwrap();
+                    parts.push(vec![piece]);
+                } else {
+                    parts.push(vec![piece]);
+                }
+            }
+
+            let mut clips: Vec<Clip> = parts.iter().map(|part| merge(part)).collect();
+            // Cut overlapping neighbours halfway between their hits
+            for i in 1..clips.len() {
+                let last_hit = parts[i - 1]
+                    .iter()
+                    .map(|p| p.hit.end)
+                    .fold(f64::MIN, f64::max);
+                let next_hit = parts[i]
+                    .iter()
+                    .map(|p| p.hit.start)
+                    .fold(f64::MAX, f64::min);
+                let (before, after) = clips.split_at_mut(i);
+                let (previous, clip) = (&mut before[i - 1], &mut after[0]);
+                if clip.start < previous.end {
+                    let boundary = ((last_hit + next_hit) / 2.0).clamp(clip.start, previous.end);
+                    previous.end = boundary;
+                    clip.start = boundary;
+                }
+            }
+            clips
+        }
+        Overflow::Drop => {
+            let mut ranked: Vec<Piece> = group;
+            ranked.sort_by(|a, b| {
+                b.priority
+                    .cmp(&a.priority)
+                    .then(b.hit.score.total_cmp(&a.hit.score))
+                    .then(a.start.total_cmp(&b.start))
+            });
+
+            let mut kept: Vec<Piece> = Vec::new();
+            for piece in ranked {
+                kept.push(piece);
+                if kept.len() > 1 && !fits(&kept, max_hits) {
+                    kept.pop();
+                }
+            }
+            kept.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+            // Dropped hits can leave gaps, so the kept pieces may form several clips
+            group_pieces(kept, config.merge.gap)
+                .iter()
+                .map(|part| merge(part))
+                .collect()
+        }
+    }
+}
+
+/// Merge a group of pieces, ordered by start, into a single clip
+fn merge(pieces: &[Piece]) -> Clip {
+    let mut hits: Vec<Hit> = pieces.iter().map(|p| p.hit.clone()).collect();
+    hits.sort_by(|a, b| a.start.total_cmp(&b.start));
+    Clip {
+        start: pieces.iter().map(|p| p.start).fold(f64::MAX, f64::min),
+        end: pieces.iter().map(|p| p.end).fold(f64::MIN, f64::max),
+        keyword: hits[0].keyword.clone(),
+        hits,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::snap;
+
+    fn word(start: f64, end: f64, text: &str) -> Timestamp {
+        segment_word(start, end, text, 0)
+    }
+
+    fn segment_word(start: f64, end: f64, text: &str, segment: usize) -> Timestamp {
+        Timestamp {
+            start,
+            end,
+            text: text.to_string(),
+            segment,
+        }
+    }
+
+    fn hit(keyword: &str, start: f64, end: f64) -> Hit {
+        Hit {
+            keyword: keyword.to_string(),
+            start,
+            end,
+            text: keyword.to_string(),
+            score: 1.0,
+        }
+    }
+
+    fn config(keywords: &[(&str, f64, f64)]) -> Config {
+        let mut config = Config::default();
+        for &(keyword, start_time, end_time) in keywords {
+            config.clips.insert(
+                keyword.to_string(),
+                ClipConfig {
+                    start_time,
+                    end_time,
+                    ..Default::default()
+                },
+            );
+        }
+        config
+    }
+
+    #[test]
+    fn test_find_hits_ignores_case_and_punctuation() {
+        let timestamps = vec![
+            word(1.0, 1.5, "Hello,"),
+            word(1.5, 2.0, "world"),
+            word(5.0, 5.5, "HELLO!"),
+            word(6.0, 6.5, "hellos"),
+        ];
+        let hits = find_hits(&timestamps, &config(&[("hello", 1.0, 1.0)]));
+
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].start, 1.0);
+        assert_eq!(hits[0].text, "Hello,");
+        assert_eq!(hits[1].start, 5.0);
+    }
+
+    #[test]
+    fn test_find_hits_matches_phrases() {
+        let timestamps = vec![
+            word(1.0, 1.4, "Game"),
+            word(1.4, 2.0, "over!"),
+            word(3.0, 3.5, "game"),
+            word(3.5, 4.0, "is"),
+            word(4.0, 4.5, "over"),
+        ];
+        let hits = find_hits(&timestamps, &config(&[("game over", 1.0, 1.0)]));
+
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].start, hits[0].end), (1.0, 2.0));
+        assert_eq!(hits[0].text, "Game over!");
+        assert_eq!(hits[0].keyword, "game over");
+    }
+
+    #[test]
+    fn test_find_hits_phrase_across_segments() {
+        let timestamps = vec![
+            segment_word(1.0, 1.5, "let's.", 0),
+            segment_word(2.0, 2.5, "Go", 1),
+        ];
+        let mut config = config(&[("let's go", 1.0, 1.0)]);
+        assert!(find_hits(&timestamps, &config).is_empty());
+
+        config.clips.get_mut("let's go").unwrap().cross_segments = true;
+        let hits = find_hits(&timestamps, &config);
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].start, hits[0].end), (1.0, 2.5));
+    }
+
+    #[test]
+    fn test_find_hits_with_pattern() -> anyhow::Result<()> {
+        let timestamps = vec![
+            word(1.0, 1.5, "Insane!"),
+            word(2.0, 2.5, "insanely"),
+            word(3.0, 3.5, "sane"),
+        ];
+        let mut config = config(&[("hype", 1.0, 1.0)]);
+        config.clips.get_mut("hype").unwrap().glob = Some("insan*".to_string());
+        config.compile_patterns()?;
+
+        let hits = find_hits(&timestamps, &config);
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().all(|hit| hit.keyword == "hype"));
+        assert_eq!(hits[1].text, "insanely");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_hits_fuzzy_phonetic_and_aliases() {
+        let timestamps = vec![
+            word(1.0, 1.5, "Volorant"),
+            word(2.0, 2.5, "pokers"),
+            word(3.0, 3.5, "valorant"),
+            word(4.0, 4.5, "Val"),
+        ];
+
+        let mut config = config(&[("valorant", 1.0, 1.0), ("poggers", 1.0, 1.0)]);
+        assert_eq!(find_hits(&timestamps, &config).len(), 1);
+
+        let valorant = config.clips.get_mut("valorant").unwrap();
+        valorant.match_mode = MatchMode::Fuzzy;
+        valorant.aliases = vec!["val".to_string()];
+        config.clips.get_mut("poggers").unwrap().match_mode = MatchMode::Phonetic;
+
+        let hits = find_hits(&timestamps, &config);
+        assert_eq!(hits.len(), 4);
+        assert_eq!(
+            (hits[0].keyword.as_str(), hits[0].score),
+            ("valorant", 1.0 - 1.0 / 8.0)
+        );
+        assert_eq!(hits[1].keyword, "poggers");
+        assert_eq!(hits[1].text, "pokers");
+        assert_eq!(hits[2].score, 1.0);
+        assert_eq!((hits[3].text.as_str(), hits[3].score), ("Val", 1.0));
+    }
+
+    #[test]
+    fn test_levenshtein_and_soundex() {
+        assert_eq!(levenshtein("poggers", "pokers"), 2);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+
+        assert_eq!(soundex("Robert"), "R163");
+        assert_eq!(soundex("Rupert"), "R163");
+        assert_eq!(soundex("Ashcraft"), "A261");
+        assert_eq!(soundex("Tymczak"), "T522");
+        assert_eq!(soundex("Pfister"), "P236");
+        assert_eq!(soundex("a"), "A000");
+        assert_eq!(soundex("123"), "");
+    }
+
+    #[test]
+    fn test_find_hits_with_query() -> anyhow::Result<()> {
+        let timestamps = vec![
+            word(1.0, 1.5, "boss"),
+            word(4.0, 4.5, "is"),
+            word(5.0, 5.5, "dead"),
+            word(60.0, 60.5, "boss"),
+            word(61.0, 61.5, "fight"),
+        ];
+        let mut config = config(&[("boss kill", 2.0, 2.0)]);
+        config.clips.get_mut("boss kill").unwrap().query =
+            Some(r#""boss" AND "dead" WITHIN 10s"#.to_string());
+        config.compile_patterns()?;
+
+        let hits = find_hits(&timestamps, &config);
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].start, hits[0].end), (1.0, 5.5));
+        assert_eq!(hits[0].text, "boss ... dead");
+        assert_eq!(hits[0].keyword, "boss kill");
+
+        config.clips.get_mut("boss kill").unwrap().query = Some("boss NEAR/0 fight".to_string());
+        config.compile_patterns()?;
+        let hits = find_hits(&timestamps, &config);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].start, 60.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_and_snap_clips_pads_and_merges() {
+        let timestamps = vec![
+            word(1.0, 1.5, "alpha"),
+            word(3.0, 3.5, "beta"),
+            word(20.0, 20.5, "alpha"),
+        ];
+        let config = config(&[("alpha", 2.0, 2.0), ("beta", 1.0, 1.0)]);
+        let mut clips = build_clips(&find_hits(&timestamps, &config), &config);
+        snap::snap_clips(&mut clips, &timestamps, &config.snap);
+
+        assert_eq!(clips.len(), 2);
+        assert_eq!((clips[0].start, clips[0].end), (0.0, 4.5));
+        assert_eq!(clips[0].keyword, "alpha");
+        assert_eq!(clips[0].keywords(), vec!["alpha", "beta"]);
+        let times: Vec<_> = clips[0].hits.iter().map(|hit| hit.start).collect();
+        assert_eq!(times, vec![1.0, 3.0]);
+        assert_eq!(clips[0].score(), 1.0);
+        assert_eq!((clips[1].start, clips[1].end), (18.0, 22.5));
+    }
+
+    #[test]
+    fn test_build_clips_min_duration() {
+        let hits = vec![hit("alpha", 0.5, 1.0), hit("alpha", 10.0, 10.5)];
+        let mut config = config(&[("alpha", 1.5, 0.5)]);
+        config.limits.min_duration = Some(4.0);
+
+        let clips = build_clips(&hits, &config);
+        assert_eq!((clips[0].start, clips[0].end), (0.0, 4.0));
+        assert_eq!((clips[1].start, clips[1].end), (7.75, 11.75));
+    }
+
+    #[test]
+    fn test_build_clips_splits_long_clips() {
+        let hits = vec![
+            hit("alpha", 10.0, 10.5),
+            hit("alpha", 20.0, 20.5),
+            hit("alpha", 30.0, 30.5),
+        ];
+        let mut config = config(&[("alpha", 5.0, 5.0)]);
+        assert_eq!(build_clips(&hits, &config).len(), 1);
+
+        config.clips.get_mut("alpha").unwrap().max_duration = Some(15.0);
+        let clips = build_clips(&hits, &config);
+        let bounds: Vec<_> = clips.iter().map(|clip| (clip.start, clip.end)).collect();
+        assert_eq!(bounds, vec![(5.0, 15.25), (15.25, 25.25), (25.25, 35.5)]);
+    }
+
+    #[test]
+    fn test_build_clips_drops_low_priority_hits() {
+        let hits = vec![
+            hit("alpha", 10.0, 10.5),
+            hit("beta", 20.0, 20.5),
+            hit("alpha", 30.0, 30.5),
+        ];
+        let mut config = config(&[("alpha", 5.0, 5.0), ("beta", 5.0, 5.0)]);
+        config.clips.get_mut("beta").unwrap().priority = 1;
+        config.limits.max_duration = Some(15.0);
+        config.limits.overflow = Overflow::Drop;
+
+        let clips = build_clips(&hits, &config);
+        assert_eq!(clips.len(), 1);
+        assert_eq!((clips[0].start, clips[0].end), (15.0, 25.5));
+        assert_eq!(clips[0].keyword, "beta");
+    }
+
+    #[test]
+    fn test_build_clips_merge_options() {
+        let hits = vec![
+            hit("alpha", 10.0, 10.5),
+            hit("beta", 12.0, 12.5),
+            hit("alpha", 14.0, 14.5),
+            hit("alpha", 20.0, 20.5),
+        ];
+        let mut config = config(&[("alpha", 0.5, 0.5), ("beta", 0.5, 0.5)]);
+        let bounds = |config: &Config| -> Vec<(f64, f64)> {
+            build_clips(&hits, config)
+                .iter()
+                .map(|clip| (clip.start, clip.end))
+                .collect()
+        };
+        assert_eq!(bounds(&config).len(), 4);
+
+        config.merge.gap = 3.0;
+        assert_eq!(bounds(&config), vec![(9.5, 15.0), (19.5, 21.0)]);
+
+        config.merge.mode = MergeMode::Keyword;
+        assert_eq!(
+            bounds(&config),
+            vec![(9.5, 15.0), (11.5, 13.0), (19.5, 21.0)]
+        );
+
+        config.merge.mode = MergeMode::Global;
+        config.merge.max_hits = Some(2);
+        assert_eq!(
+            bounds(&config),
+            vec![(9.5, 13.0), (13.5, 15.0), (19.5, 21.0)]
+        );
+
+        config.merge.mode = MergeMode::Never;
+        config.merge.gap = 100.0;
+        assert_eq!(bounds(&config).len(), 4);
+    }
+}
@@ -0,0 +1,222 @@
+use super::cache::{Clip, Timestamp};
+use super::config::{SnapConfig, SnapMode};
+
+/// Most speech-free room kept between a silence-snapped boundary and the speech
+const SILENCE_MARGIN: f64 = 0.1;
+
+/// Times where clips may start or end
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Edges {
+    /// Times where speech starts, in seconds
+    pub starts: Vec<f64>,
+    /// Times where speech ends, in seconds
+    pub ends: Vec<f64>,
+}
+
+/// Whether a word ends a sentence, ignoring closing quotes and brackets
+fn ends_sentence(word: &str) -> bool {
+    word.trim_end_matches(['"', '\'', ')', ']'])
+        .ends_with(['.', '!', '?', '…'])
+}
+
+/// Find the segment or sentence edges in the transcription
+///
+/// # Arguments
+/// * `timestamps` - Word-level timestamps, ordered by time
+/// * `mode` - Which edges to find
+pub fn transcript_edges(timestamps: &[Timestamp], mode: SnapMode) -> Edges {
+    let mut edges = Edges::default();
+    for (i, timestamp) in timestamps.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|i| &timestamps[i]);
+        let next = timestamps.get(i + 1);
+        let (starts, ends) = match mode {
+            SnapMode::None | SnapMode::Silence => return edges,
+            SnapMode::Segment => (
+                previous.is_none_or(|p| p.segment != timestamp.segment),
+                next.is_none_or(|n| n.segment != timestamp.segment),
+            ),
+            // The transcript starts and ends a sentence even without punctuation
+            SnapMode::Sentence => (
+                previous.is_none_or(|p| ends_sentence(&p.text)),
+                next.is_none() || ends_sentence(&timestamp.text),
+            ),
+        };
+        if starts {
+            edges.starts.push(timestamp.start);
+        }
+        if ends {
+            edges.ends.push(timestamp.end);
+        }
+    }
+    edges
+}
+
+/// Turn pauses in the audio into edges
+///
+/// Clips start shortly before the speech after a pause and end shortly
+/// after the speech before it, staying inside the pause.
+///
+/// # Arguments
+/// * `silences` - Start and end of each pause in seconds
+pub fn silence_edges(silences: &[(f64, f64)]) -> Edges {
+    let mut edges = Edges::default();
+    for &(start, end) in silences {
+        let margin = SILENCE_MARGIN.min((end - start) / 2.0);
+        edges.starts.push(end - margin);
+        edges.ends.push(start + margin);
+    }
+    edges
+}
+
+/// The edge closest to `target` within `[min, max]`, if there is one
+fn nearest(edges: &[f64], target: f64, min: f64, max: f64) -> Option<f64> {
+    edges
+        .iter()
+        .copied()
+        .filter(|&edge| edge >= min && edge <= max)
+        .min_by(|a, b| (a - target).abs().total_cmp(&(b - target).abs()))
+}
+
+/// Move each clip boundary to the nearest edge within `tolerance` seconds
+///
+/// Boundaries never move past the clip's own hits or into a neighbouring
+/// clip, and stay where they are when no edge is close enough.
+///
+/// # Arguments
+/// * `clips` - Clips ordered by start
+/// * `edges` - Candidate start and end times
+/// * `tolerance` - Furthest a boundary may move, in seconds
+pub fn snap_to_edges(clips: &mut [Clip], edges: &Edges, tolerance: f64) {
+    for i in 0..clips.len() {
+        let previous_end = i.checked_sub(1).map_or(0.0, |i| clips[i].end);
+        let next_start = clips.get(i + 1).map_or(f64::MAX, |next| next.start);
+        let clip = &mut clips[i];
+        let first_hit = clip
+            .hits
+            .iter()
+            .map(|hit| hit.start)
+            .fold(clip.end, f64::min);
+        let last_hit = clip
+            .hits
+            .iter()
+            .map(|hit| hit.end)
+            .fold(clip.start, f64::max);
+
+        let min = (clip.start - tolerance).max(previous_end.min(clip.start));
+        let max = (clip.start + tolerance).min(first_hit);
+        if let Some(start) = nearest(&edges.starts, clip.start, min, max) {
+            clip.start = start;
+        }
+
+        let min = (clip.end - tolerance).max(last_hit).max(clip.start);
+        let max = (clip.end + tolerance).min(next_start.max(clip.end));
+        if let Some(end) = nearest(&edges.ends, clip.end, min, max) {
+            clip.end = end;
+        }
+    }
+}
+
+/// Snap clip boundaries to the segment or sentence edges set in `[snap]`
+///
+/// Silence snapping needs the audio, see `silence_edges`.
+///
+/// # Arguments
+/// * `clips` - Clips ordered by start
+/// * `timestamps` - Word-level timestamps the clips were found in
+/// * `config` - Snapping mode and tolerance
+pub fn snap_clips(clips: &mut [Clip], timestamps: &[Timestamp], config: &SnapConfig) {
+    if config.mode == SnapMode::None {
+        return;
+    }
+    let edges = transcript_edges(timestamps, config.mode);
+    snap_to_edges(clips, &edges, config.tolerance);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::search::Hit;
+
+    fn word(start: f64, end: f64, text: &str, segment: usize) -> Timestamp {
+        Timestamp {
+            start,
+            end,
+            text: text.to_string(),
+            segment,
+        }
+    }
+
+    fn transcript() -> Vec<Timestamp> {
+        vec![
+            word(0.0, 0.5, "Okay", 0),
+            word(0.5, 1.0, "so.", 0),
+            word(2.0, 2.5, "This", 1),
+            word(2.5, 3.0, "is", 1),
+            word(3.0, 3.5, "insane!", 1),
+            word(3.5, 4.0, "Right", 1),
+            word(4.0, 4.5, "there.", 1),
+            word(6.0, 6.5, "Next", 2),
+        ]
+    }
+
+    fn clip(start: f64, end: f64, hit: (f64, f64)) -> Clip {
+        Clip {
+            start,
+            end,
+            keyword: "insane".to_string(),
+            hits: vec![Hit {
+                keyword: "insane".to_string(),
+                start: hit.0,
+                end: hit.1,
+                text: "insane!".to_string(),
+                score: 1.0,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_transcript_edges() {
+        let edges = transcript_edges(&transcript(), SnapMode::Segment);
+        assert_eq!(edges.starts, vec![0.0, 2.0, 6.0]);
+        assert_eq!(edges.ends, vec![1.0, 4.5, 6.5]);
+
+        let edges = transcript_edges(&transcript(), SnapMode::Sentence);
+        assert_eq!(edges.starts, vec![0.0, 2.0, 3.5, 6.0]);
+        assert_eq!(edges.ends, vec![1.0, 3.5, 4.5, 6.5]);
+    }
+
+    #[test]
+    fn test_silence_edges() {
+        let edges = silence_edges(&[(1.0, 2.0), (5.0, 5.1)]);
+        assert_eq!(edges.starts, vec![1.9, 5.05]);
+        assert_eq!(edges.ends, vec![1.1, 5.05]);
+
+        let mut clips = vec![clip(1.5, 4.8, (3.0, 3.5))];
+        snap_to_edges(&mut clips, &edges, 0.5);
+        assert_eq!((clips[0].start, clips[0].end), (1.9, 5.05));
+    }
+
+    #[test]
+    fn test_snap_clips() {
+        let mut config = SnapConfig {
+            mode: SnapMode::Segment,
+            tolerance: 1.0,
+            ..Default::default()
+        };
+        let mut clips = vec![clip(2.5, 4.0, (3.0, 3.5))];
+        snap_clips(&mut clips, &transcript(), &config);
+        assert_eq!((clips[0].start, clips[0].end), (2.0, 4.5));
+
+        // The end may not move before the hit, and nothing is close enough to the start
+        config.mode = SnapMode::Sentence;
+        config.tolerance = 0.25;
+        let mut clips = vec![clip(1.5, 3.4, (3.0, 3.5))];
+        snap_clips(&mut clips, &transcript(), &config);
+        assert_eq!((clips[0].start, clips[0].end), (1.5, 3.5));
+
+        // The last word ends a sentence even without punctuation
+        let mut clips = vec![clip(5.9, 6.4, (6.0, 6.4))];
+        snap_clips(&mut clips, &transcript(), &config);
+        assert_eq!((clips[0].start, clips[0].end), (6.0, 6.5));
+    }
+}
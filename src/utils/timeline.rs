@@ -0,0 +1,637 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::cache::Clip;
+use super::ffmpeg::VideoInfo;
+
+/// Edit decision list formats that can be exported
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TimelineFormat {
+    /// CMX3600 edit decision list
+    Edl,
+    /// Final Cut Pro XML 1.9, also read by DaVinci Resolve and Premiere
+    Fcpxml,
+    /// OpenTimelineIO JSON
+    Otio,
+}
+
+impl TimelineFormat {
+    /// File extension for the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TimelineFormat::Edl => "edl",
+            TimelineFormat::Fcpxml => "fcpxml",
+            TimelineFormat::Otio => "otio",
+        }
+    }
+}
+
+/// Converts between frame counts and SMPTE timecode at a given frame rate
+#[derive(Debug, Clone, Copy)]
+pub struct Timecode {
+    /// Exact frame rate as a numerator and denominator
+    frame_rate: (u32, u32),
+    /// Frames per timecode second, e.g. 30 for 29.97
+    base: u64,
+    /// Whether frame numbers are skipped to keep 29.97/59.94 in sync with the clock
+    drop_frame: bool,
+}
+
+impl Timecode {
+    /// Timecode settings for a video, using drop-frame when its start timecode does
+    pub fn for_video(info: &VideoInfo) -> Self {
+        let base = info.fps().round().max(1.0) as u64;
+        let drop_frame = base.is_multiple_of(30)
+            && info.frame_rate.1 != 1
+            && info
+                .timecode
+                .as_deref()
+                .is_some_and(|tc| tc.contains([';', '.']));
+        Self {
+            frame_rate: info.frame_rate,
+            base,
+            drop_frame,
+        }
+    }
+
+    /// Frames dropped at the start of each minute in drop-frame mode
+    fn dropped(&self) -> u64 {
+        if self.drop_frame {
+            self.base / 15
+        } else {
+            0
+        }
+    }
+
+    /// The frame count of 01:00:00:00, where editors start their timelines
+    ///
+    /// In drop-frame mode this is fewer than an hour of frames, since frame
+    /// numbers are skipped along the way.
+    pub fn hour(&self) -> u64 {
+        self.parse("01:00:00:00").unwrap_or(self.base * 3600)
+    }
+
+    /// The frame counting mode as written in an EDL header
+    pub fn fcm(&self) -> &'static str {
+        if self.drop_frame {
+            "DROP FRAME"
+        } else {
+            "NON-DROP FRAME"
+        }
+    }
+
+    /// Number of whole frames in `seconds`
+    pub fn frames(&self, seconds: f64) -> u64 {
+        (seconds.max(0.0) * self.frame_rate.0 as f64 / self.frame_rate.1 as f64).round() as u64
+    }
+
+    /// Formats a frame count as `HH:MM:SS:FF`, or `HH:MM:SS;FF` for drop-frame
+    pub fn format(&self, frames: u64) -> String {
+        let dropped = self.dropped();
+        let mut frames = frames;
+        if dropped > 0 {
+            let per_ten_minutes = self.base * 600 - dropped * 9;
+            let per_minute = self.base * 60 - dropped;
+            let tens = frames / per_ten_minutes;
+            let rest = frames % per_ten_minutes;
+            frames += dropped * 9 * tens;
+            if rest > dropped {
+                frames += dropped * ((rest - dropped) / per_minute);
+            }
+        }
+
+        let separator = if self.drop_frame { ';' } else { ':' };
+        format!(
+            "{:02}:{:02}:{:02}{}{:02}",
+            frames / (self.base * 3600) % 24,
+            frames / (self.base * 60) % 60,
+            frames / self.base % 60,
+            separator,
+            frames % self.base
+        )
+    }
+
+    /// Formats a length in frames as `HH:MM:SS:FF`
+    ///
+    /// Durations always count every frame, so drop-frame labelling does not apply.
+    pub fn format_duration(&self, frames: u64) -> String {
+        Self {
+            drop_frame: false,
+            ..*self
+        }
+        .format(frames)
+    }
+
+    /// Parses `HH:MM:SS:FF` (or `;`/`.` drop-frame separators) into a frame count
+    pub fn parse(&self, timecode: &str) -> Option<u64> {
+        let parts: Vec<u64> = timecode
+            .split([':', ';', '.'])
+            .map(|part| part.parse().ok())
+            .collect::<Option<_>>()?;
+        let [hours, minutes, seconds, frames] = parts[..] else {
+            return None;
+        };
+
+        let total_minutes = hours * 60 + minutes;
+        let frames = ((total_minutes * 60 + seconds) * self.base + frames)
+            .checked_sub(self.dropped() * (total_minutes - total_minutes / 10))?;
+        Some(frames)
+    }
+
+    /// Formats a frame count as an FCPXML rational time, e.g. `1001/30000s`
+    fn rational(&self, frames: u64) -> String {
+        if frames == 0 {
+            return String::from("0s");
+        }
+        let (num, den) = self.frame_rate;
+        format!("{}/{}s", frames * den as u64, num)
+    }
+}
+
+/// A clip converted to frames, ready to be placed on a timeline
+struct Event<'a> {
+    /// Marker or clip name
+    name: &'a str,
+    /// First frame in the source, including its start timecode
+    source_in: u64,
+    /// Frame after the last frame in the source
+    source_out: u64,
+    /// Position of the clip on the timeline, from the start of the timeline
+    record_in: u64,
+}
+
+impl Event<'_> {
+    fn duration(&self) -> u64 {
+        self.source_out - self.source_in
+    }
+}
+
+/// Lay the clips out back to back, skipping any that are shorter than a frame
+fn events<'a>(clips: &'a [Clip], timecode: &Timecode, source_start: u64) -> Vec<Event<'a>> {
+    let mut record_in = 0;
+    let mut events = Vec::new();
+    for clip in clips {
+        let source_in = source_start + timecode.frames(clip.start);
+        let source_out = source_start + timecode.frames(clip.end);
+        if source_out <= source_in {
+            continue;
+        }
+        events.push(Event {
+            name: &clip.keyword,
+            source_in,
+            source_out,
+            record_in,
+        });
+        record_in += source_out - source_in;
+    }
+    events
+}
+
+/// Render clips as a CMX3600 edit decision list
+///
+/// Each clip becomes an event with the keyword as a comment. The record
+/// side starts at 01:00:00:00 as most editors expect.
+pub fn to_edl(
+    clips: &[Clip],
+    media: &Path,
+    info: &VideoInfo,
+    title: &str,
+    timecode: &Timecode,
+) -> String {
+    let source_start = source_start(info, timecode);
+    let record_start = timecode.hour();
+    let file_name = media
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+
+    let mut out = format!("TITLE: {}\nFCM: {}\n", title, timecode.fcm());
+    for (i, event) in events(clips, timecode, source_start).iter().enumerate() {
+        let record_in = record_start + event.record_in;
+        out.push_str(&format!(
+            "\n{:03}  AX       AA/V  C        {} {} {} {}\n",
+            i + 1,
+            timecode.format(event.source_in),
+            timecode.format(event.source_out),
+            timecode.format(record_in),
+            timecode.format(record_in + event.duration())
+        ));
+        out.push_str(&format!("* FROM CLIP NAME: {}\n", file_name));
+        out.push_str(&format!("* COMMENT: {}\n", event.name));
+    }
+    out
+}
+
+/// Render clips as an FCPXML 1.9 project
+///
+/// Each clip becomes an `asset-clip` named after its keyword, with a marker
+/// on its first frame.
+pub fn to_fcpxml(
+    clips: &[Clip],
+    media: &Path,
+    info: &VideoInfo,
+    title: &str,
+    timecode: &Timecode,
+) -> String {
+    let source_start = source_start(info, timecode);
+    let events = events(clips, timecode, source_start);
+    let sequence_duration: u64 = events.iter().map(Event::duration).sum();
+    let frame_duration = timecode.rational(1);
+    let tc_format = if timecode.drop_frame { "DF" } else { "NDF" };
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE fcpxml>\n");
+    out.push_str("<fcpxml version=\"1.9\">\n  <resources>\n");
+    out.push_str(&format!(
+        "    <format id=\"r1\" frameDuration=\"{}\" width=\"{}\" height=\"{}\"/>\n",
+        frame_duration, info.width, info.height
+    ));
+    out.push_str(&format!(
+        "    <asset id=\"r2\" name=\"{}\" start=\"{}\" duration=\"{}\" hasVideo=\"1\" hasAudio=\"1\" format=\"r1\">\n",
+        xml_escape(&media.file_stem().unwrap_or_default().to_string_lossy()),
+        timecode.rational(source_start),
+        timecode.rational(timecode.frames(info.duration))
+    ));
+    out.push_str(&format!(
+        "      <media-rep kind=\"original-media\" src=\"{}\"/>\n",
+        xml_escape(&file_url(media))
+    ));
+    out.push_str("    </asset>\n  </resources>\n");
+    out.push_str("  <library>\n    <event name=\"clive\">\n");
+    out.push_str(&format!(
+        "      <project name=\"{}\">\n        <sequence format=\"r1\" duration=\"{}\" tcStart=\"0s\" tcFormat=\"{}\">\n          <spine>\n",
+        xml_escape(title),
+        timecode.rational(sequence_duration),
+        tc_format
+    ));
+    for event in &events {
+        let name = xml_escape(event.name);
+        out.push_str(&format!(
+            "            <asset-clip ref=\"r2\" name=\"{}\" offset=\"{}\" start=\"{}\" duration=\"{}\" format=\"r1\" tcFormat=\"{}\">\n",
+            name,
+            timecode.rational(event.record_in),
+            timecode.rational(event.source_in),
+            timecode.rational(event.duration()),
+            tc_format
+        ));
+        out.push_str(&format!(
+            "              <marker start=\"{}\" duration=\"{}\" value=\"{}\"/>\n",
+            timecode.rational(event.source_in),
+            frame_duration,
+            name
+        ));
+        out.push_str("            </asset-clip>\n");
+    }
+    out.push_str("          </spine>\n        </sequence>\n      </project>\n    </event>\n  </library>\n</fcpxml>\n");
+    out
+}
+
+/// Render clips as an OpenTimelineIO timeline with one video and one audio track
+///
+/// Each clip is named after its keyword and carries a marker spanning the clip.
+pub fn to_otio(
+    clips: &[Clip],
+    media: &Path,
+    info: &VideoInfo,
+    title: &str,
+    timecode: &Timecode,
+) -> Result<String> {
+    let rate = info.fps();
+    let time = |frames: u64| {
+        json!({
+            "OTIO_SCHEMA": "RationalTime.1",
+            "rate": rate,
+            "value": frames as f64,
+        })
+    };
+    let range = |start: u64, duration: u64| {
+        json!({
+            "OTIO_SCHEMA": "TimeRange.1",
+            "start_time": time(start),
+            "duration": time(duration),
+        })
+    };
+
+    let source_start = source_start(info, timecode);
+    let events = events(clips, timecode, source_start);
+    let track = |name: &str, kind: &str| {
+        let children: Vec<_> = events
+            .iter()
+            .map(|event| {
+                json!({
+                    "OTIO_SCHEMA": "Clip.2",
+                    "name": event.name,
+                    "source_range": range(event.source_in, event.duration()),
+                    "media_references": {
+                        "DEFAULT_MEDIA": {
+                            "OTIO_SCHEMA": "ExternalReference.1",
+                            "name": media.file_name().unwrap_or_default().to_string_lossy(),
+                            "target_url": file_url(media),
+                            "available_range": range(source_start, timecode.frames(info.duration)),
+                            "metadata": {},
+                        }
+                    },
+                    "active_media_reference_key": "DEFAULT_MEDIA",
+                    "markers": [{
+                        "OTIO_SCHEMA": "Marker.2",
+                        "name": event.name,
+                        "color": "RED",
+                        "comment": "",
+                        "marked_range": range(event.source_in, event.duration()),
+                        "metadata": {},
+                    }],
+                    "effects": [],
+                    "enabled": true,
+                    "metadata": {},
+                })
+            })
+            .collect();
+        json!({
+            "OTIO_SCHEMA": "Track.1",
+            "name": name,
+            "kind": kind,
+            "children": children,
+            "source_range": null,
+            "markers": [],
+            "effects": [],
+            "enabled": true,
+            "metadata": {},
+        })
+    };
+
+    let timeline = json!({
+        "OTIO_SCHEMA": "Timeline.1",
+        "name": title,
+        "global_start_time": time(timecode.hour()),
+        "tracks": {
+            "OTIO_SCHEMA": "Stack.1",
+            "name": "tracks",
+            "children": [track("V1", "Video"), track("A1", "Audio")],
+            "source_range": null,
+            "markers": [],
+            "effects": [],
+            "enabled": true,
+            "metadata": {},
+        },
+        "metadata": {},
+    });
+
+    Ok(serde_json::to_string_pretty(&timeline)?)
+}
+
+/// The start timecode of the media in frames, or 0 when it has none
+pub fn source_start(info: &VideoInfo, timecode: &Timecode) -> u64 {
+    info.timecode
+        .as_deref()
+        .and_then(|tc| timecode.parse(tc))
+        .unwrap_or(0)
+}
+
+/// Escape text for use in an XML attribute
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Build a `file://` URL for a path, percent-encoding anything unsafe
+fn file_url(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let path = path.to_string_lossy().replace('\\', "/");
+
+    let mut url = String::from("file://");
+    if !path.starts_with('/') {
+        url.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' | b':' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    url
+}
+
+/// Render clips in the given timeline format
+///
+/// # Arguments
+/// * `clips` - Clips to place on the timeline, in order
+/// * `format` - Format to render
+/// * `media` - Path to the source video the clips refer to
+/// * `info` - Frame rate, resolution and start timecode of the source video
+/// * `title` - Name of the timeline
+pub fn render(
+    clips: &[Clip],
+    format: TimelineFormat,
+    media: &Path,
+    info: &VideoInfo,
+    title: &str,
+) -> Result<String> {
+    let timecode = Timecode::for_video(info);
+    Ok(match format {
+        TimelineFormat::Edl => to_edl(clips, media, info, title, &timecode),
+        TimelineFormat::Fcpxml => to_fcpxml(clips, media, info, title, &timecode),
+        TimelineFormat::Otio => to_otio(clips, media, info, title, &timecode)?,
+    })
+}
+
+/// Write the timeline to `<output_dir>/<name>.<ext>` and return the written path
+///
+/// # Arguments
+/// * `clips` - Clips to place on the timeline, in order
+/// * `format` - Format to write
+/// * `media` - Path to the source video the clips refer to
+/// * `info` - Frame rate, resolution and start timecode of the source video
+/// * `output_dir` - Directory where the file will be saved
+/// * `name` - File name without extension, also used as the timeline name
+pub fn write(
+    clips: &[Clip],
+    format: TimelineFormat,
+    media: &Path,
+    info: &VideoInfo,
+    output_dir: &Path,
+    name: &str,
+) -> Result<PathBuf> {
+    let path = output_dir.join(format!("{}.{}", name, format.extension()));
+    let contents = render(clips, format, media, info, name)?;
+    fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(frame_rate: (u32, u32), timecode: Option<&str>) -> VideoInfo {
+        VideoInfo {
+            frame_rate,
+            width: 1920,
+            height: 1080,
+            duration: 600.0,
+            timecode: timecode.map(String::from),
+        }
+    }
+
+    fn clips() -> Vec<Clip> {
+        vec![
+            Clip {
+                start: 10.0,
+                end: 12.0,
+                keyword: "hello".to_string(),
+                hits: Vec::new(),
+            },
+            Clip {
+                start: 30.5,
+                end: 31.0,
+                keyword: "R&D".to_string(),
+                hits: Vec::new(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_timecode_non_drop_frame() {
+        let timecode = Timecode::for_video(&info((25, 1), None));
+        assert_eq!(timecode.format(0), "00:00:00:00");
+        assert_eq!(timecode.format(25 * 3600 + 26), "01:00:01:01");
+        assert_eq!(timecode.parse("01:00:01:01"), Some(25 * 3600 + 26));
+        assert_eq!(timecode.frames(1.5), 38);
+    }
+
+    #[test]
+    fn test_timecode_drop_frame() {
+        let timecode = Timecode::for_video(&info((30000, 1001), Some("00:00:00;00")));
+        assert!(timecode.drop_frame);
+        // Frame numbers 00 and 01 are skipped at the start of every minute but every tenth
+        assert_eq!(timecode.format(1799), "00:00:59;29");
+        assert_eq!(timecode.format(1800), "00:01:00;02");
+        assert_eq!(timecode.format(17982), "00:10:00;00");
+        for frames in [0, 1799, 1800, 17982, 107892, 123456] {
+            assert_eq!(timecode.parse(&timecode.format(frames)), Some(frames));
+        }
+
+        // Without a drop-frame start timecode 29.97 is written as non-drop
+        let timecode = Timecode::for_video(&info((30000, 1001), None));
+        assert!(!timecode.drop_frame);
+        assert_eq!(timecode.format(1800), "00:01:00:00");
+    }
+
+    #[test]
+    fn test_to_edl() {
+        let info = info((25, 1), Some("10:00:00:00"));
+        let edl = render(
+            &clips(),
+            TimelineFormat::Edl,
+            Path::new("/media/in.mp4"),
+            &info,
+            "test",
+        )
+        .unwrap();
+
+        assert_eq!(
+            edl,
+            "TITLE: test\n\
+             FCM: NON-DROP FRAME\n\
+             \n\
+             001  AX       AA/V  C        10:00:10:00 10:00:12:00 01:00:00:00 01:00:02:00\n\
+             * FROM CLIP NAME: in.mp4\n\
+             * COMMENT: hello\n\
+             \n\
+             002  AX       AA/V  C        10:00:30:13 10:00:31:00 01:00:02:00 01:00:02:12\n\
+             * FROM CLIP NAME: in.mp4\n\
+             * COMMENT: R&D\n"
+        );
+    }
+
+    #[test]
+    fn test_to_edl_drop_frame() {
+        let info = info((30000, 1001), Some("00:00:00;00"));
+        let timecode = Timecode::for_video(&info);
+        assert_eq!(timecode.hour(), 107892);
+        assert_eq!(timecode.format(timecode.hour()), "01:00:00;00");
+
+        let edl = render(
+            &clips(),
+            TimelineFormat::Edl,
+            Path::new("/media/in.mp4"),
+            &info,
+            "test",
+        )
+        .unwrap();
+
+        assert_eq!(
+            edl,
+            "TITLE: test\n\
+             FCM: DROP FRAME\n\
+             \n\
+             001  AX       AA/V  C        00:00:10;00 00:00:12;00 01:00:00;00 01:00:02;00\n\
+             * FROM CLIP NAME: in.mp4\n\
+             * COMMENT: hello\n\
+             \n\
+             002  AX       AA/V  C        00:00:30;14 00:00:30;29 01:00:02;00 01:00:02;15\n\
+             * FROM CLIP NAME: in.mp4\n\
+             * COMMENT: R&D\n"
+        );
+    }
+
+    #[test]
+    fn test_to_fcpxml() {
+        let info = info((30000, 1001), None);
+        let xml = render(
+            &clips(),
+            TimelineFormat::Fcpxml,
+            Path::new("/media/my in.mp4"),
+            &info,
+            "test",
+        )
+        .unwrap();
+
+        assert!(xml.contains("<fcpxml version=\"1.9\">"));
+        assert!(xml.contains("frameDuration=\"1001/30000s\""));
+        assert!(xml.contains("src=\"file:///media/my%20in.mp4\""));
+        assert!(xml.contains(
+            "<asset-clip ref=\"r2\" name=\"hello\" offset=\"0s\" start=\"300300/30000s\" duration=\"60060/30000s\""
+        ));
+        assert!(xml.contains(
+            "<marker start=\"300300/30000s\" duration=\"1001/30000s\" value=\"hello\"/>"
+        ));
+        assert!(xml.contains("name=\"R&amp;D\" offset=\"60060/30000s\""));
+    }
+
+    #[test]
+    fn test_to_otio() -> Result<()> {
+        let info = info((24, 1), None);
+        let otio = render(
+            &clips(),
+            TimelineFormat::Otio,
+            Path::new("/media/in.mp4"),
+            &info,
+            "test",
+        )?;
+        let timeline: serde_json::Value = serde_json::from_str(&otio)?;
+
+        let video = &timeline["tracks"]["children"][0];
+        assert_eq!(video["kind"], "Video");
+        assert_eq!(video["children"].as_array().unwrap().len(), 2);
+
+        let clip = &video["children"][0];
+        assert_eq!(clip["name"], "hello");
+        assert_eq!(clip["source_range"]["start_time"]["value"], 240.0);
+        assert_eq!(clip["source_range"]["duration"]["value"], 48.0);
+        assert_eq!(clip["markers"][0]["name"], "hello");
+        assert_eq!(
+            clip["media_references"]["DEFAULT_MEDIA"]["target_url"],
+            "file:///media/in.mp4"
+        );
+
+        Ok(())
+    }
+}